@@ -0,0 +1,80 @@
+//! Opens a headless renderer on a large document and reports how long
+//! the first frame took, run with `cargo run --release --example document_startup`.
+
+use std::time::Instant;
+
+use notes_editor::{
+    app::AppRender,
+    config::AppConfig,
+    widgets::{
+        path::{PathWidget, PathWidgetDesc},
+        square::{SquareWidget, SquareWidgetDesc},
+        Widget, WidgetRender,
+    },
+};
+use winit::dpi::PhysicalSize;
+
+const WIDGETS: usize = 500;
+
+fn document() -> Vec<Box<dyn WidgetRender>> {
+    (0..WIDGETS)
+        .map(|i| {
+            let offset = i as f32 % 90.0;
+            if i % 2 == 0 {
+                SquareWidget::new(SquareWidgetDesc {
+                    width: 10.0,
+                    height: 10.0,
+                    x: offset,
+                    y: offset,
+                    color: [1.0, 1.0, 1.0, 1.0],
+                    ..Default::default()
+                })
+                .build() as Box<dyn WidgetRender>
+            } else {
+                PathWidget::new(PathWidgetDesc {
+                    points: (0..10).map(|j| [offset + j as f32, offset]).collect(),
+                    width: 1.0,
+                    color: [0.0, 0.0, 0.0, 1.0],
+                    ..Default::default()
+                })
+                .build()
+            }
+        })
+        .collect()
+}
+
+async fn open(bulk: bool, report: bool) -> anyhow::Result<()> {
+    let widgets = document();
+    let start = Instant::now();
+    let mut renderer =
+        AppRender::new_headless(PhysicalSize::new(256, 256), &AppConfig::default()).await?;
+    let attached = start.elapsed();
+    if bulk {
+        renderer.add_widgets(widgets);
+    } else {
+        for i in widgets {
+            renderer.add_widget(i);
+        }
+    }
+    let added = start.elapsed() - attached;
+    renderer.capture()?;
+    if !report {
+        return Ok(());
+    }
+    println!(
+        "{}: GPU up in {attached:?}, widgets added in {added:?}, first frame after {:?}",
+        if bulk { "add_widgets" } else { "add_widget" },
+        start.elapsed()
+    );
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    println!("{WIDGETS} widgets");
+    // Driver start-up is much slower the first time, and renderers are
+    // opened one after the other since some GL drivers cannot have two
+    open(false, false).await?;
+    open(false, true).await?;
+    open(true, true).await
+}
@@ -0,0 +1,59 @@
+//! Drives an editor window from an event loop owned by the caller instead
+//! of `App::run`, run with `cargo run --example embedded`.
+
+use notes_editor::{
+    app::{WindowConfig, WindowState},
+    config::AppConfig,
+    widgets::{
+        square::{SquareWidget, SquareWidgetDesc},
+        Widget,
+    },
+};
+use winit::{
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let event_loop = EventLoop::new();
+    let square = SquareWidget::new(SquareWidgetDesc {
+        width: 20.0,
+        height: 20.0,
+        x: 40.0,
+        y: 40.0,
+        color: [1.0, 1.0, 1.0, 1.0],
+        ..Default::default()
+    })
+    .build();
+    let mut window = WindowState::new(
+        &event_loop,
+        WindowConfig {
+            title: "Embedded".to_string(),
+            widgets: vec![square],
+            ..Default::default()
+        },
+        &AppConfig::default(),
+    )
+    .await?;
+    event_loop.run(move |event, _, control_flow| match event {
+        Event::WindowEvent {
+            event: WindowEvent::CloseRequested,
+            ..
+        } => *control_flow = ControlFlow::Exit,
+        Event::WindowEvent { event, .. } => window.handle_event(event),
+        Event::RedrawRequested(_) => {
+            if let Err(x) = window.render_once() {
+                eprintln!("{:?}", x);
+            }
+        }
+        Event::MainEventsCleared => {
+            *control_flow = ControlFlow::Wait;
+            window.flush_input();
+            if window.needs_redraw() {
+                window.get_renderer().get_window().request_redraw();
+            }
+        }
+        _ => {}
+    });
+}
@@ -0,0 +1,46 @@
+//! Records two seconds of a spinner as PNG frames, run with
+//! `cargo run --example record -- <dir>`. Assemble them with, e.g.,
+//! `ffmpeg -framerate 30 -i <dir>/frame_%05d.png spinner.gif`.
+
+use std::{path::PathBuf, time::Duration};
+
+use notes_editor::{
+    app::AppRender,
+    config::AppConfig,
+    record::{record, Recording},
+    widgets::{
+        spinner::{SpinnerWidget, SpinnerWidgetDesc},
+        Widget,
+    },
+};
+use winit::dpi::PhysicalSize;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let dir = std::env::args_os()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("recording"));
+    let mut renderer =
+        AppRender::new_headless(PhysicalSize::new(128, 128), &AppConfig::default()).await?;
+    renderer.add_widget(
+        SpinnerWidget::new(SpinnerWidgetDesc {
+            width: 80.0,
+            height: 80.0,
+            x: 10.0,
+            y: 10.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            thickness: 0.2,
+            spinning: true,
+            ..Default::default()
+        })
+        .build(),
+    );
+    let recording = Recording {
+        duration: Duration::from_secs(2),
+        fps: 30,
+    };
+    let frames = record(&mut renderer, recording, &dir)?;
+    println!("Wrote {} frames to {}", frames.len(), dir.display());
+    Ok(())
+}
@@ -0,0 +1,67 @@
+//! Rapidly adds and removes widgets on a headless renderer and reports
+//! how many GPU buffers that took, run with `cargo run --release --example widget_churn`.
+
+use std::time::Instant;
+
+use notes_editor::{
+    app::AppRender,
+    config::AppConfig,
+    widgets::{
+        path::{PathWidget, PathWidgetDesc},
+        square::{SquareWidget, SquareWidgetDesc},
+        Widget,
+    },
+};
+use winit::dpi::PhysicalSize;
+
+const FRAMES: usize = 1000;
+const WIDGETS_PER_FRAME: usize = 20;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut renderer =
+        AppRender::new_headless(PhysicalSize::new(256, 256), &AppConfig::default()).await?;
+    let start = Instant::now();
+    for frame in 0..FRAMES {
+        for i in 0..WIDGETS_PER_FRAME {
+            let offset = (frame + i) as f32 % 90.0;
+            if i % 2 == 0 {
+                renderer.add_widget(
+                    SquareWidget::new(SquareWidgetDesc {
+                        width: 10.0,
+                        height: 10.0,
+                        x: offset,
+                        y: offset,
+                        color: [1.0, 1.0, 1.0, 1.0],
+                        ..Default::default()
+                    })
+                    .build(),
+                );
+            } else {
+                renderer.add_widget(
+                    PathWidget::new(PathWidgetDesc {
+                        points: (0..=i).map(|j| [offset + j as f32, offset]).collect(),
+                        width: 1.0,
+                        color: [0.0, 0.0, 0.0, 1.0],
+                        ..Default::default()
+                    })
+                    .build(),
+                );
+            }
+        }
+        while renderer.get_widgets().len() > 0 {
+            renderer.remove_widget(0);
+        }
+    }
+    let stats = renderer.buffer_stats();
+    let requested = stats.allocated + stats.reused;
+    println!(
+        "{FRAMES} frames of {WIDGETS_PER_FRAME} widgets in {:?}",
+        start.elapsed()
+    );
+    println!(
+        "{requested} buffers requested, {} allocated, {} reused",
+        stats.allocated, stats.reused
+    );
+    Ok(())
+}
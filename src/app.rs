@@ -1,66 +1,346 @@
-use std::future::Future;
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    future::Future,
+    ops::Range,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
-use anyhow::{Context, Result};
-use wgpu::{util::DeviceExt, *};
+use anyhow::{bail, Context, Result};
+use tokio::runtime::Handle;
+use wgpu::*;
 use winit::{
-    dpi::{LogicalSize, PhysicalSize},
-    event::{Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
-    window::Window,
+    dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize},
+    event::{
+        ElementState, Event, KeyboardInput, ModifiersState, MouseButton, Touch, TouchPhase,
+        VirtualKeyCode, WindowEvent,
+    },
+    event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
+    window::{Window, WindowBuilder},
+};
+
+use crate::{
+    buffer_pool::{BufferPool, BufferPoolStats},
+    bus::{EventBus, Payload},
+    color,
+    config::AppConfig,
+    coords::{
+        ndc_to_percent, percent_out_of_area, percent_to_pixel_rect, rect_into_area,
+        rect_out_of_area,
+    },
+    geometry::{Point, Rect},
+    ink::{self, EraserState, InkState, Tool},
+    pipeline::PipelineKey,
+    pointer::{ClickCounter, PointerCapture},
+    theme::{Theme, ThemePreference},
+    widgets::{
+        self,
+        shadow::ShadowWidget,
+        square::{SquareWidget, SquareWidgetDesc},
+        PointerEvent, Widget, WidgetRender,
+    },
+};
+
+/// Emitted with `Payload::Number` when the window moves to a display
+/// with a different scale factor.
+pub const SCALE_FACTOR_CHANGED: &str = "window.scale_factor_changed";
+
+// Until a theme sets one
+const DEFAULT_BACKGROUND: color::Color = color::Color::new(0.0, 1.0, 0.0, 1.0);
+// Longest step animations take in one frame
+const MAX_FRAME_TIME: Duration = Duration::from_millis(100);
+const WHOLE_WINDOW: Rect = Rect {
+    x: 0.0,
+    y: 0.0,
+    width: 100.0,
+    height: 100.0,
 };
 
-use crate::widgets::WidgetRender;
+/*
+ * Damage tracking
+ * Frames are drawn into a persistent offscreen texture rather than the
+ * surface, whose textures may hold any earlier frame once double or
+ * triple buffered. Only the area changed since the last frame (the
+ * union of the old and new extents of every changed widget) is cleared
+ * and redrawn, scissored, then the whole texture is copied to the surface.
+ */
 
+/// The widgets of a window and, once attached, the GPU state drawing them.
+/// Without a GPU every document method still works, only drawing does not.
 pub struct AppRender {
+    gpu: Option<Gpu>,
+    pub size: PhysicalSize<u32>,
+    widgets: Vec<WidgetObject>,
+    // Handed to the next widget added
+    next_id: WidgetId,
+    // Widget-space area to redraw in the next frame
+    damage: Cell<Option<Rect>>,
+    background: color::Color,
+    events: EventBus,
+    // Physical pixels per logical pixel
+    scale_factor: f64,
+    stats: Cell<RenderStats>,
+    content_margin: Margin,
+}
+
+/// Identifies a widget for as long as it is in the draw list, unlike its
+/// index, which shifts as widgets below it are removed or reordered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct WidgetId(u64);
+
+/// Top, right, bottom and left insets in logical pixels.
+pub type Margin = (f32, f32, f32, f32);
+
+/// What drawing the last frame took.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RenderStats {
+    /// Including the background, shadows and copying to the window.
+    pub draw_calls: usize,
+    pub widgets_drawn: usize,
+    /// Widgets skipped since they lie outside the redrawn area.
+    pub widgets_culled: usize,
+    /// CPU time spent encoding and submitting the frame.
+    pub frame_time: Duration,
+}
+
+struct Gpu {
     _instance: Instance,
-    surface: Surface,
+    // Both are None when rendering headless
+    surface: Option<Surface>,
     _adapter: Adapter,
     device: Device,
     queue: Queue,
-    window: Window,
-    pub size: PhysicalSize<u32>,
-    pub config: SurfaceConfiguration,
-    widgets: Vec<WidgetObject>,
+    window: Option<Window>,
+    config: SurfaceConfiguration,
+    // Filled while uploading, which only borrows the renderer
+    pipelines: RefCell<HashMap<PipelineKey, Rc<RenderPipeline>>>,
+    // Keyed by the WGSL source itself, so sources never collide
+    shaders: RefCell<HashMap<String, Rc<ShaderModule>>>,
+    buffers: RefCell<BufferPool>,
+    frame: Frame,
+    // Only needed to present to a surface
+    blit: Option<Blit>,
+    // Clears damaged areas, a clear op would clear the whole frame
+    background: Option<Mesh>,
+}
+
+/// The persistent texture frames are drawn into.
+struct Frame {
+    texture: Texture,
+    view: TextureView,
+    blit_group: Option<BindGroup>,
+}
+
+/// Copies the frame to the surface, premultiplying it on the way for
+/// surfaces composited that way, as the frame holds straight alpha.
+struct Blit {
+    pipeline: RenderPipeline,
+    layout: BindGroupLayout,
 }
 
 impl AppRender {
-    pub async fn new(window: Window) -> Result<Self> {
+    pub async fn new(window: Window, app_config: &AppConfig) -> Result<Self> {
+        let mut renderer = Self::new_offline(window.inner_size());
+        renderer.attach(window, app_config).await?;
+        Ok(renderer)
+    }
+    /// Creates a renderer without a window, drawing only through `capture`.
+    pub async fn new_headless(size: PhysicalSize<u32>, app_config: &AppConfig) -> Result<Self> {
+        let mut renderer = Self::new_offline(size);
+        renderer.attach_headless(app_config).await?;
+        Ok(renderer)
+    }
+    /// Creates a renderer without any GPU state, until `attach` or
+    /// `attach_headless` is called it only keeps track of its widgets.
+    pub fn new_offline(size: PhysicalSize<u32>) -> Self {
+        Self {
+            gpu: None,
+            size,
+            widgets: vec![],
+            next_id: WidgetId::default(),
+            damage: Cell::new(None),
+            background: DEFAULT_BACKGROUND,
+            events: EventBus::default(),
+            scale_factor: 1.0,
+            stats: Cell::default(),
+            content_margin: (0.0, 0.0, 0.0, 0.0),
+        }
+    }
+    /// Brings up the GPU for `window` and uploads the widgets added so far.
+    pub async fn attach(&mut self, window: Window, app_config: &AppConfig) -> Result<()> {
         let size = window.inner_size();
         let instance = Self::init_instance();
         let surface = unsafe { instance.create_surface(&window) }
             .with_context(|| "Failed to create surface")?;
-        let adapter = Self::init_adapter(&instance, &surface)
+        let adapter = Self::init_adapter(&instance, Some(&surface))
             .await
             .with_context(|| "Failed to request adapter")?;
-        let (device, queue) = Self::init_device_and_queue(&adapter)
-            .await
-            .with_context(|| "Failed to got Device and Queue")?;
+        let (device, queue) = Self::init_device_and_queue(&adapter, app_config).await?;
+        Self::init_error_handler(&device, app_config);
         let surface_capabilities = surface.get_capabilities(&adapter);
-        let config = Self::init_config(surface_capabilities, size);
+        let config = Self::init_config(
+            surface_capabilities,
+            surface_size(&device, size),
+            app_config.transparent,
+        );
         surface.configure(&device, &config);
-        Ok(Self {
+        self.size = size;
+        self.scale_factor = window.scale_factor();
+        let premultiply = config.alpha_mode == CompositeAlphaMode::PreMultiplied;
+        let blit = Blit::new(&device, config.format, premultiply);
+        let frame = Frame::new(
+            &device,
+            config.format,
+            frame_size(&device, size),
+            Some(&blit),
+        );
+        self.install(Gpu {
             _instance: instance,
-            surface,
+            surface: Some(surface),
             _adapter: adapter,
             device,
             queue,
-            size,
+            window: Some(window),
             config,
-            window,
-            widgets: vec![],
-        })
+            pipelines: RefCell::default(),
+            shaders: RefCell::default(),
+            buffers: RefCell::default(),
+            frame,
+            blit: Some(blit),
+            background: None,
+        });
+        self.clear_surface();
+        Ok(())
+    }
+    /// Presents a frame of just the background, so the window does not show
+    /// whatever the surface held until content is drawn.
+    fn clear_surface(&self) {
+        let gpu = self.gpu();
+        let Some(surface) = &gpu.surface else {
+            return;
+        };
+        // The first real frame clears anyway, so failing here is harmless
+        let Ok(output) = surface.get_current_texture() else {
+            return;
+        };
+        let view = output.texture.create_view(&Default::default());
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Encode Clear"),
+            });
+        encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Clear Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(clear_color(self.surface_background())),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        gpu.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+    }
+    /// Brings up the GPU without a window and uploads the widgets added so far.
+    pub async fn attach_headless(&mut self, app_config: &AppConfig) -> Result<()> {
+        let instance = Self::init_instance();
+        let adapter = Self::init_adapter(&instance, None)
+            .await
+            .with_context(|| "Failed to request adapter")?;
+        let (device, queue) = Self::init_device_and_queue(&adapter, app_config).await?;
+        Self::init_error_handler(&device, app_config);
+        let size = frame_size(&device, self.size);
+        let config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            format: TextureFormat::Rgba8UnormSrgb,
+            width: size.width,
+            height: size.height,
+            present_mode: PresentMode::Fifo,
+            alpha_mode: CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+        let frame = Frame::new(&device, config.format, size, None);
+        self.install(Gpu {
+            _instance: instance,
+            surface: None,
+            _adapter: adapter,
+            device,
+            queue,
+            window: None,
+            config,
+            pipelines: RefCell::default(),
+            shaders: RefCell::default(),
+            buffers: RefCell::default(),
+            frame,
+            blit: None,
+            background: None,
+        });
+        Ok(())
+    }
+    fn install(&mut self, gpu: Gpu) {
+        self.gpu = Some(gpu);
+        self.upload_background();
+        self.upload_all();
+        self.damage(Some(WHOLE_WINDOW));
     }
-    fn init_device_and_queue(
+    fn upload_background(&mut self) {
+        let background = self.upload(&SquareWidget::new(SquareWidgetDesc {
+            width: WHOLE_WINDOW.width,
+            height: WHOLE_WINDOW.height,
+            x: WHOLE_WINDOW.x,
+            y: WHOLE_WINDOW.y,
+            color: self.background.to_array(),
+            ..Default::default()
+        }));
+        if let Some(gpu) = &mut self.gpu {
+            if let Some(old) = std::mem::replace(&mut gpu.background, background) {
+                old.recycle(&mut gpu.buffers.borrow_mut());
+            }
+        }
+    }
+    /// The background as the surface composites it, for drawing to the
+    /// surface directly. The frame holds straight alpha, see `Blit`.
+    fn surface_background(&self) -> color::Color {
+        let premultiplied = self
+            .gpu
+            .as_ref()
+            .is_some_and(|gpu| gpu.config.alpha_mode == CompositeAlphaMode::PreMultiplied);
+        if premultiplied {
+            self.background.premultiplied()
+        } else {
+            self.background
+        }
+    }
+    async fn init_device_and_queue(
         adapter: &Adapter,
-    ) -> impl Future<Output = Result<(Device, Queue), RequestDeviceError>> + Send {
-        adapter.request_device(
-            &DeviceDescriptor {
-                label: Some("devque"),
-                features: Features::empty(),
-                limits: Limits::default(),
-            },
-            None,
-        )
+        app_config: &AppConfig,
+    ) -> Result<(Device, Queue)> {
+        let features = app_config.device_features(adapter.features())?;
+        app_config.check_limits(&adapter.limits())?;
+        let device_and_queue = adapter
+            .request_device(
+                &DeviceDescriptor {
+                    label: Some("devque"),
+                    features,
+                    limits: app_config.limits.clone(),
+                },
+                None,
+            )
+            .await
+            .with_context(|| "Failed to got Device and Queue")?;
+        Ok(device_and_queue)
+    }
+    fn init_error_handler(device: &Device, app_config: &AppConfig) {
+        if app_config.validation {
+            device.on_uncaptured_error(Box::new(|error| {
+                eprintln!("wgpu validation error: {}", error);
+            }));
+        }
     }
     fn init_instance() -> Instance {
         Instance::new(InstanceDescriptor {
@@ -70,17 +350,18 @@ impl AppRender {
     }
     fn init_adapter(
         instance: &Instance,
-        surface: &Surface,
+        surface: Option<&Surface>,
     ) -> impl Future<Output = Option<Adapter>> + Send {
         instance.request_adapter(&RequestAdapterOptions {
             power_preference: PowerPreference::default(),
             force_fallback_adapter: false,
-            compatible_surface: Some(surface),
+            compatible_surface: surface,
         })
     }
     fn init_config(
         surface_capabilities: SurfaceCapabilities,
         size: PhysicalSize<u32>,
+        transparent: bool,
     ) -> SurfaceConfiguration {
         let format = surface_capabilities
             .formats
@@ -94,141 +375,1371 @@ impl AppRender {
             width: size.width,
             height: size.height,
             present_mode: surface_capabilities.present_modes[0],
-            alpha_mode: surface_capabilities.alpha_modes[0],
+            alpha_mode: alpha_mode(&surface_capabilities.alpha_modes, transparent),
             view_formats: vec![],
         }
     }
     // Getter/Setters
+    fn gpu(&self) -> &Gpu {
+        self.gpu
+            .as_ref()
+            .expect("No GPU attached to the renderer yet")
+    }
+    pub fn has_gpu(&self) -> bool {
+        self.gpu.is_some()
+    }
     pub fn get_device(&self) -> &Device {
-        &self.device
+        &self.gpu().device
+    }
+    /// Device features granted at startup, including the optional ones
+    /// the adapter supports.
+    pub fn get_features(&self) -> Features {
+        self.gpu().device.features()
+    }
+    pub fn get_config(&self) -> &SurfaceConfiguration {
+        &self.gpu().config
     }
     pub fn get_window(&self) -> &Window {
-        &self.window
+        self.gpu()
+            .window
+            .as_ref()
+            .expect("Headless renderers have no window")
+    }
+    /// Physical pixels per logical pixel of the window, 1.0 without one.
+    pub fn get_scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+    /// Sets the scale factor, emitting `SCALE_FACTOR_CHANGED` with the new
+    /// one so widgets sized in pixels can be laid out again.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        if scale_factor == self.scale_factor {
+            return;
+        }
+        self.scale_factor = scale_factor;
+        self.events
+            .emit(SCALE_FACTOR_CHANGED, Payload::Number(scale_factor as f32));
+        // The content margin is in logical pixels
+        self.damage(Some(WHOLE_WINDOW));
+        self.relayout_pixels();
+    }
+    pub fn get_content_margin(&self) -> Margin {
+        self.content_margin
+    }
+    /// Insets the area widgets are laid out in from the window's edges.
+    /// Widget space (0.0 - 100.0) then spans only the inner area, and
+    /// nothing is drawn over the margin but the background.
+    pub fn set_content_margin(&mut self, margin: Margin) {
+        self.content_margin = margin;
+        self.damage(Some(WHOLE_WINDOW));
+        self.relayout_pixels();
+    }
+    /// The area inside the content margin, in percent of the window.
+    pub fn content_area(&self) -> Rect {
+        let (top, right, bottom, left) = self.content_margin;
+        let scale = self.scale_factor as f32;
+        let width = self.size.width as f32;
+        let height = self.size.height as f32;
+        let x = left * scale / width * 100.0;
+        let y = top * scale / height * 100.0;
+        Rect {
+            x,
+            y,
+            width: (100.0 - x - right * scale / width * 100.0).max(0.0),
+            height: (100.0 - y - bottom * scale / height * 100.0).max(0.0),
+        }
+    }
+    /// Widget units a logical pixel spans along x and y, see
+    /// `Widget::on_pixel_size`.
+    pub fn logical_pixel_size(&self) -> [f32; 2] {
+        let area = self.content_area();
+        let scale = self.scale_factor as f32;
+        // Of the content area, in logical pixels
+        let width = self.size.width as f32 / scale * area.width / 100.0;
+        let height = self.size.height as f32 / scale * area.height / 100.0;
+        [100.0 / width, 100.0 / height]
+    }
+    // Widgets measured in pixels change with the pixel size
+    fn relayout_pixels(&mut self) {
+        let pixel_size = self.logical_pixel_size();
+        for index in 0..self.widgets.len() {
+            if self.widgets[index].spec.on_pixel_size(pixel_size) {
+                self.reupload(index);
+            }
+        }
+    }
+    /// Converts a position in logical pixels to widget space (0.0 - 100.0).
+    pub fn logical_to_percent(&self, position: LogicalPosition<f64>) -> Point {
+        self.physical_to_percent(position.to_physical(self.scale_factor))
+    }
+    /// Converts a position in physical pixels to widget space (0.0 - 100.0).
+    pub fn physical_to_percent(&self, position: PhysicalPosition<f64>) -> Point {
+        let window = [
+            (position.x / self.size.width as f64 * 100.0) as f32,
+            (position.y / self.size.height as f64 * 100.0) as f32,
+        ];
+        percent_out_of_area(window, self.content_area())
+    }
+    pub fn get_background(&self) -> color::Color {
+        self.background
+    }
+    /// Sets the color the canvas is cleared to behind all widgets.
+    pub fn set_background(&mut self, background: color::Color) {
+        self.background = background;
+        self.upload_background();
+        self.damage(Some(WHOLE_WINDOW));
+    }
+    pub fn get_widgets(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = &dyn WidgetRender> + ExactSizeIterator {
+        self.widgets.iter().map(|i| i.spec.as_ref())
+    }
+    /// Calls `visit` with every widget, nested ones included, and its path.
+    /// See `widgets::walk`.
+    pub fn walk_widgets<'a>(&'a self, visit: impl FnMut(&[usize], &'a dyn WidgetRender)) {
+        widgets::walk(self.get_widgets(), visit)
+    }
+    /// Path of the first widget, nested ones included, matching `predicate`.
+    pub fn find_widget(
+        &self,
+        predicate: impl FnMut(&dyn WidgetRender) -> bool,
+    ) -> Option<Vec<usize>> {
+        widgets::find(self.get_widgets(), predicate)
+    }
+    /// Union of all widget bounds, or None if there are no widgets.
+    pub fn content_bounds(&self) -> Option<Rect> {
+        self.get_widgets()
+            .map(|i| i.get_bounds())
+            .reduce(|bounds, i| bounds.union(&i))
+    }
+    /// Index of the top-most widget `point` lands on, see `Widget::hit_test`.
+    pub fn widget_at(&self, point: Point) -> Option<usize> {
+        self.get_widgets().rposition(|i| i.hit_test(point))
+    }
+    /// Identity of the widget at `index`, see `WidgetId`.
+    pub fn widget_id(&self, index: usize) -> WidgetId {
+        self.widgets[index].id
+    }
+    /// Current index of the widget `id`, or None once it was removed.
+    pub fn index_of(&self, id: WidgetId) -> Option<usize> {
+        self.widgets.iter().position(|i| i.id == id)
+    }
+    /// Forwards pointer input to the widget at `index`, re-uploading it if it changed.
+    pub fn send_pointer_event(&mut self, index: usize, event: PointerEvent) {
+        let emitter = self.events.emitter();
+        if self.widgets[index].spec.on_pointer(event, emitter) {
+            self.reupload(index);
+        }
+    }
+    /// Appends a widget to the draw list, uploading it if there is a GPU,
+    /// and returns its index.
+    pub fn add_widget(&mut self, mut object: Box<dyn WidgetRender>) -> usize {
+        object.on_pixel_size(self.logical_pixel_size());
+        let mesh = self.upload(object.as_ref());
+        let extent = extent_of(object.as_ref());
+        self.damage(extent);
+        let id = self.next_id;
+        self.next_id = WidgetId(id.0 + 1);
+        self.widgets.push(WidgetObject {
+            id,
+            spec: object,
+            mesh,
+            extent,
+        });
+        self.widgets.len() - 1
+    }
+    /// Appends widgets in bulk, as when opening a document, and returns
+    /// their indices.
+    pub fn add_widgets(
+        &mut self,
+        objects: impl IntoIterator<Item = Box<dyn WidgetRender>>,
+    ) -> Range<usize> {
+        let objects = objects.into_iter();
+        let start = self.widgets.len();
+        self.widgets.reserve(objects.size_hint().0);
+        for object in objects {
+            self.add_widget(object);
+        }
+        start..self.widgets.len()
+    }
+    /// Changes the widget at `index` in place and re-uploads its geometry.
+    pub fn edit_widget(&mut self, index: usize, edit: impl FnOnce(&mut dyn WidgetRender)) {
+        edit(self.widgets[index].spec.as_mut());
+        self.reupload(index);
+    }
+    /// Replaces the widget at `index` and re-uploads its geometry.
+    pub fn update_widget(&mut self, index: usize, mut object: Box<dyn WidgetRender>) {
+        object.on_pixel_size(self.logical_pixel_size());
+        self.widgets[index].spec = object;
+        self.reupload(index);
+    }
+    /// Removes the widget at `index` from the draw list and hands it back,
+    /// so it can be added again later.
+    pub fn remove_widget(&mut self, index: usize) -> Box<dyn WidgetRender> {
+        let widget = self.widgets.remove(index);
+        self.damage(widget.extent);
+        self.recycle(widget.mesh);
+        widget.spec
+    }
+    /// Moves the widget at `index` to the top of the draw order and
+    /// returns its new index. Like removing, this shifts the indices of
+    /// the widgets in between.
+    pub fn bring_to_front(&mut self, index: usize) -> usize {
+        self.check_index(index);
+        self.move_widget(index, self.widgets.len() - 1)
+    }
+    /// Moves the widget at `index` to the bottom of the draw order.
+    pub fn send_to_back(&mut self, index: usize) -> usize {
+        self.check_index(index);
+        self.move_widget(index, 0)
+    }
+    /// Swaps the widget at `index` with the one drawn above it, if any.
+    pub fn raise(&mut self, index: usize) -> usize {
+        self.check_index(index);
+        self.move_widget(index, (index + 1).min(self.widgets.len() - 1))
+    }
+    /// Swaps the widget at `index` with the one drawn below it, if any.
+    pub fn lower(&mut self, index: usize) -> usize {
+        self.check_index(index);
+        self.move_widget(index, index.saturating_sub(1))
+    }
+    // Before working out where to move to, which underflows without widgets
+    fn check_index(&self, index: usize) {
+        assert!(
+            index < self.widgets.len(),
+            "Widget index {index} out of bounds, there are {} widgets",
+            self.widgets.len()
+        );
+    }
+    fn move_widget(&mut self, from: usize, to: usize) -> usize {
+        let widget = self.widgets.remove(from);
+        // Only where it overlaps others looks different, but that is
+        // not worth working out
+        self.damage(widget.extent);
+        self.widgets.insert(to, widget);
+        to
+    }
+    /// Stats of the last frame drawn by `render` or `capture`.
+    pub fn last_stats(&self) -> RenderStats {
+        self.stats.get()
+    }
+    /// Buffer pool counts, all zero without a GPU.
+    pub fn buffer_stats(&self) -> BufferPoolStats {
+        self.gpu
+            .as_ref()
+            .map(|gpu| gpu.buffers.borrow().stats())
+            .unwrap_or_default()
+    }
+    fn upload_all(&mut self) {
+        for index in 0..self.widgets.len() {
+            // Meshes from an earlier GPU cannot be recycled into this one
+            self.widgets[index].mesh = self.upload(self.widgets[index].spec.as_ref());
+        }
+    }
+    fn reupload(&mut self, index: usize) {
+        let old = self.widgets[index].mesh.take();
+        self.recycle(old);
+        self.damage(self.widgets[index].extent);
+        let extent = extent_of(self.widgets[index].spec.as_ref());
+        self.widgets[index].extent = extent;
+        self.damage(extent);
+        self.widgets[index].mesh = self.upload(self.widgets[index].spec.as_ref());
+    }
+    /// Area that will be redrawn in the next frame, None if nothing changed.
+    pub fn get_damage(&self) -> Option<Rect> {
+        self.damage.get()
+    }
+    fn damage(&self, area: Option<Rect>) {
+        // Off-screen changes would only widen the redrawn area
+        let area = area.and_then(|i| i.intersection(&WHOLE_WINDOW));
+        let damage = match (self.damage.get(), area) {
+            (Some(damage), Some(area)) => Some(damage.union(&area)),
+            (damage, area) => damage.or(area),
+        };
+        self.damage.set(damage);
+    }
+    /// Uploads the widget and its shadow, None without a GPU.
+    fn upload(&self, object: &dyn WidgetRender) -> Option<Mesh> {
+        let render_pipeline = self.get_cached_pipeline(object)?;
+        let shadow = shadow_of(object).and_then(|i| self.upload(i.as_ref()));
+        let gpu = self.gpu();
+        let mut mesh = Mesh::new(
+            &mut gpu.buffers.borrow_mut(),
+            &gpu.device,
+            &gpu.queue,
+            object,
+            render_pipeline,
+        );
+        mesh.shadow = shadow.map(Box::new);
+        Some(mesh)
+    }
+    fn recycle(&self, mesh: Option<Mesh>) {
+        if let (Some(gpu), Some(mesh)) = (&self.gpu, mesh) {
+            mesh.recycle(&mut gpu.buffers.borrow_mut());
+        }
+    }
+    /// The shader module compiled from WGSL `source`, compiled on first use
+    /// and shared from then on whichever widget type asks.
+    pub fn get_shader_module(&self, source: &str) -> Rc<ShaderModule> {
+        let gpu = self.gpu();
+        if let Some(shader) = gpu.shaders.borrow().get(source) {
+            return shader.clone();
+        }
+        let shader = Rc::new(gpu.device.create_shader_module(ShaderModuleDescriptor {
+            label: None,
+            source: ShaderSource::Wgsl(Cow::Borrowed(source)),
+        }));
+        gpu.shaders
+            .borrow_mut()
+            .insert(source.to_string(), shader.clone());
+        shader
+    }
+    /// Widgets of the same type and blend mode share a pipeline.
+    fn get_cached_pipeline(&self, object: &dyn WidgetRender) -> Option<Rc<RenderPipeline>> {
+        let gpu = self.gpu.as_ref()?;
+        let key = (object.type_name(), object.get_blend_mode());
+        if let Some(pipeline) = gpu.pipelines.borrow().get(&key) {
+            return Some(pipeline.clone());
+        }
+        let pipeline = Rc::new(object.get_pipeline(self));
+        gpu.pipelines.borrow_mut().insert(key, pipeline.clone());
+        Some(pipeline)
     }
     // Methods
+    /// Draws a frame to the window, does nothing when headless or without a GPU.
     pub fn render(&self) -> Result<(), SurfaceError> {
-        let background = self.surface.get_current_texture()?;
-        let background_view = background.texture.create_view(&Default::default());
-        let mut encoder = self
+        let Some(
+            gpu @ Gpu {
+                surface: Some(surface),
+                blit: Some(blit),
+                ..
+            },
+        ) = &self.gpu
+        else {
+            return Ok(());
+        };
+        let output = surface.get_current_texture()?;
+        let output_view = output.texture.create_view(&Default::default());
+        let mut encoder = gpu
             .device
             .create_command_encoder(&CommandEncoderDescriptor {
                 label: Some("Encode"),
             });
+        let start = Instant::now();
+        let mut stats = self.draw_damage(&mut encoder);
         {
             let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-                label: Some("Render Pass"),
+                label: Some("Blit Pass"),
                 color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &background_view,
+                    view: &output_view,
                     resolve_target: None,
                     ops: Operations {
-                        load: LoadOp::Clear(Color::GREEN),
+                        load: LoadOp::Clear(clear_color(self.surface_background())),
                         store: true,
                     },
                 })],
                 depth_stencil_attachment: None,
             });
-            for i in &self.widgets {
-                render_pass.set_pipeline(&i.render_pipeline);
-                render_pass.set_vertex_buffer(0, i.vertex_buffer.slice(..));
-                render_pass.set_index_buffer(i.index_buffer.slice(..), IndexFormat::Uint16);
-                render_pass.draw_indexed(0..i.index_len, 0, 0..1)
+            render_pass.set_pipeline(&blit.pipeline);
+            if let Some(blit_group) = &gpu.frame.blit_group {
+                render_pass.set_bind_group(0, blit_group, &[]);
             }
+            render_pass.draw(0..3, 0..1);
+            stats.draw_calls += 1;
         }
-        self.queue.submit(std::iter::once(encoder.finish()));
-        background.present();
+        gpu.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+        stats.frame_time = start.elapsed();
+        self.stats.set(stats);
         Ok(())
     }
+    /// Size of the frames `capture` reads back, the window's unless scaled
+    /// down to fit the texture limit.
+    pub fn get_frame_size(&self) -> PhysicalSize<u32> {
+        let texture = &self.gpu().frame.texture;
+        PhysicalSize::new(texture.width(), texture.height())
+    }
+    /// Brings the frame up to date and reads it back as tightly packed
+    /// rows of 4-byte pixels in `config.format`, `get_frame_size` large.
+    pub fn capture(&self) -> Result<Vec<u8>> {
+        let Some(gpu) = &self.gpu else {
+            bail!("No GPU attached to capture with");
+        };
+        let size = gpu.frame.texture.size();
+        // Buffer rows have to be padded to the copy alignment
+        let row_len = size.width * 4;
+        let padded_row_len =
+            row_len.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = gpu.device.create_buffer(&BufferDescriptor {
+            label: Some("Capture Buffer"),
+            size: (padded_row_len * size.height) as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Encode Capture"),
+            });
+        let start = Instant::now();
+        let mut stats = self.draw_damage(&mut encoder);
+        encoder.copy_texture_to_buffer(
+            gpu.frame.texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_len),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        gpu.queue.submit(std::iter::once(encoder.finish()));
+        stats.frame_time = start.elapsed();
+        self.stats.set(stats);
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        gpu.device.poll(Maintain::Wait);
+        receiver
+            .recv()?
+            .with_context(|| "Failed to map capture buffer")?;
+        let pixels = slice
+            .get_mapped_range()
+            .chunks(padded_row_len as usize)
+            .flat_map(|row| &row[..row_len as usize])
+            .copied()
+            .collect();
+        buffer.unmap();
+        Ok(pixels)
+    }
+    /// Redraws the damaged part of the frame, if any.
+    fn draw_damage(&self, encoder: &mut CommandEncoder) -> RenderStats {
+        let gpu = self.gpu();
+        let (width, height) = (gpu.frame.texture.width(), gpu.frame.texture.height());
+        let mut stats = RenderStats::default();
+        let culled = self.widgets.iter().filter(|i| i.mesh.is_some()).count();
+        let Some(damage) = self.damage.take() else {
+            stats.widgets_culled = culled;
+            return stats;
+        };
+        // The margin is only ever cleared, together with the content
+        let area = self.content_area();
+        let content = percent_to_pixel_rect(area, width, height);
+        let scissor = percent_to_pixel_rect(rect_into_area(damage, area), width, height);
+        let whole = match (content, scissor) {
+            (Some(content), Some(scissor)) => content == scissor,
+            (Some(_), None) => {
+                stats.widgets_culled = culled;
+                return stats;
+            }
+            (None, _) => true,
+        };
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &gpu.frame.view,
+                resolve_target: None,
+                ops: Operations {
+                    load: if whole {
+                        LoadOp::Clear(clear_color(self.background))
+                    } else {
+                        LoadOp::Load
+                    },
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        let (Some(_), Some([x, y, scissor_width, scissor_height])) = (content, scissor) else {
+            stats.widgets_culled = culled;
+            return stats;
+        };
+        // Everything in the scissor gets redrawn, not just the damage
+        let redrawn = rect_out_of_area(
+            Rect {
+                x: x as f32 * 100.0 / width as f32,
+                y: y as f32 * 100.0 / height as f32,
+                width: scissor_width as f32 * 100.0 / width as f32,
+                height: scissor_height as f32 * 100.0 / height as f32,
+            },
+            area,
+        );
+        render_pass.set_viewport(
+            area.x * width as f32 / 100.0,
+            area.y * height as f32 / 100.0,
+            area.width * width as f32 / 100.0,
+            area.height * height as f32 / 100.0,
+            0.0,
+            1.0,
+        );
+        if !whole {
+            render_pass.set_scissor_rect(x, y, scissor_width, scissor_height);
+            if let Some(background) = &gpu.background {
+                background.draw(&mut render_pass);
+                stats.draw_calls += 1;
+            }
+        }
+        for widget in &self.widgets {
+            let Some(mesh) = &widget.mesh else {
+                continue;
+            };
+            if !widget.extent.is_some_and(|i| i.intersects(&redrawn)) {
+                stats.widgets_culled += 1;
+                continue;
+            }
+            if let Some(shadow) = &mesh.shadow {
+                shadow.draw(&mut render_pass);
+                stats.draw_calls += 1;
+            }
+            mesh.draw(&mut render_pass);
+            stats.draw_calls += 1;
+            stats.widgets_drawn += 1;
+        }
+        stats
+    }
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
+        // Minimized windows report a zero size, which no texture can have
+        if new_size.width == 0 || new_size.height == 0 {
+            return;
+        }
         self.size = new_size;
-        self.config.height = new_size.height;
-        self.config.width = new_size.width;
-        self.surface.configure(&self.device, &self.config);
+        self.relayout_pixels();
+        let Some(gpu) = &mut self.gpu else {
+            return;
+        };
+        let size = frame_size(&gpu.device, new_size);
+        if let Some(surface) = &gpu.surface {
+            let surface_size = surface_size(&gpu.device, new_size);
+            gpu.config.width = surface_size.width;
+            gpu.config.height = surface_size.height;
+            surface.configure(&gpu.device, &gpu.config);
+        } else {
+            gpu.config.width = size.width;
+            gpu.config.height = size.height;
+        }
+        gpu.frame = Frame::new(&gpu.device, gpu.config.format, size, gpu.blit.as_ref());
+        self.damage(Some(WHOLE_WINDOW));
+    }
+    /// The window's event bus, delivered on the next `update`.
+    pub fn events(&mut self) -> &mut EventBus {
+        &mut self.events
+    }
+    /// Whether the next frame would draw anything new.
+    pub fn needs_redraw(&self) -> bool {
+        self.damage.get().is_some() || self.get_widgets().any(|i| i.is_animating())
     }
-    pub fn update(&mut self) -> Result<()> {
+    /// Advances widget animations by `dt`, then delivers queued events.
+    pub fn update(&mut self, dt: Duration) -> Result<()> {
+        for index in 0..self.widgets.len() {
+            if self.widgets[index].spec.update(dt) {
+                self.reupload(index);
+            }
+        }
+        // Handlers get the renderer, so the bus is moved out meanwhile and
+        // whatever they emit or subscribe lands in its stand-in
+        let mut events = std::mem::take(&mut self.events);
+        events.dispatch(self);
+        events.merge(std::mem::take(&mut self.events));
+        self.events = events;
         Ok(())
     }
 }
 
-#[derive(Debug)]
+fn clear_color(color: color::Color) -> Color {
+    Color {
+        r: color.r as f64,
+        g: color.g as f64,
+        b: color.b as f64,
+        a: color.a as f64,
+    }
+}
+
+/// How the surface's alpha is composited, one that shows the desktop
+/// through if `transparent` and the surface supports it.
+fn alpha_mode(supported: &[CompositeAlphaMode], transparent: bool) -> CompositeAlphaMode {
+    // Widgets are drawn with straight alpha, so post-multiplied needs no
+    // conversion, pre-multiplied has the blit convert every pixel
+    let preferred = [
+        CompositeAlphaMode::PostMultiplied,
+        CompositeAlphaMode::PreMultiplied,
+    ];
+    let found = preferred.into_iter().find(|i| supported.contains(i));
+    match found {
+        Some(mode) if transparent => mode,
+        _ => {
+            if transparent {
+                eprintln!("The surface cannot be transparent, drawing it opaque");
+            }
+            if supported.contains(&CompositeAlphaMode::Opaque) {
+                CompositeAlphaMode::Opaque
+            } else {
+                supported[0]
+            }
+        }
+    }
+}
+
+/// Size of the frame texture for a window of `size`, scaled down to fit
+/// the device's texture limit. The blit stretches smaller frames over the
+/// surface, which beats failing to create the texture.
+fn frame_size(device: &Device, size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+    let max = device.limits().max_texture_dimension_2d;
+    let fitted = fit_within(size, max);
+    if fitted != size {
+        eprintln!(
+            "{}x{} exceeds the GPU's texture limit of {max}, drawing at {}x{}",
+            size.width, size.height, fitted.width, fitted.height
+        );
+    }
+    fitted
+}
+
+/// Size to configure the surface of a window of `size` at. Some platforms
+/// need it to match the window, so it is only clamped to the extents the
+/// surface supports. wgpu does not expose those, the texture limit bounds
+/// the surface's textures all the same.
+fn surface_size(device: &Device, size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+    clamp_within(size, device.limits().max_texture_dimension_2d)
+}
+
+/// `size` with each side clamped to 1 - `max`, unlike `fit_within`
+/// without keeping the aspect ratio.
+fn clamp_within(size: PhysicalSize<u32>, max: u32) -> PhysicalSize<u32> {
+    PhysicalSize::new(size.width.clamp(1, max), size.height.clamp(1, max))
+}
+
+/// `size` scaled down, keeping its aspect ratio, until neither side is
+/// over `max`.
+fn fit_within(size: PhysicalSize<u32>, max: u32) -> PhysicalSize<u32> {
+    let longest = size.width.max(size.height);
+    if longest <= max {
+        return size;
+    }
+    let scale = |side: u32| ((side as u64 * max as u64 / longest as u64) as u32).max(1);
+    PhysicalSize::new(scale(size.width), scale(size.height))
+}
+
+/// Shadow to draw behind `object`, following its current bounds.
+fn shadow_of(object: &dyn WidgetRender) -> Option<Box<dyn WidgetRender>> {
+    let desc = object.get_shadow()?;
+    Some(ShadowWidget::new(object.get_bounds(), desc).build())
+}
+
+/// Widget-space area covered by the vertices of `object` and its shadow.
+fn extent_of(object: &dyn WidgetRender) -> Option<Rect> {
+    let shadow = shadow_of(object);
+    let vertices = object
+        .get_vertices()
+        .iter()
+        .chain(shadow.iter().flat_map(|i| i.get_vertices()));
+    vertices
+        .map(|i| {
+            let [x, y] = ndc_to_percent([i.position[0], i.position[1]]);
+            Rect {
+                x,
+                y,
+                width: 0.0,
+                height: 0.0,
+            }
+        })
+        .reduce(|extent, i| extent.union(&i))
+}
+
 struct WidgetObject {
+    pub id: WidgetId,
+    pub spec: Box<dyn WidgetRender>,
+    // None while there is no GPU to upload to
+    pub mesh: Option<Mesh>,
+    // Cached extent_of(spec), for damage tracking
+    pub extent: Option<Rect>,
+}
+
+/// A widget's geometry on the GPU.
+struct Mesh {
     pub vertex_buffer: Buffer,
     pub index_buffer: Buffer,
     pub _vertex_len: u32,
     pub index_len: u32,
-    pub render_pipeline: RenderPipeline,
+    pub render_pipeline: Rc<RenderPipeline>,
+    pub shadow: Option<Box<Mesh>>,
+}
+
+impl Mesh {
+    fn new(
+        pool: &mut BufferPool,
+        device: &Device,
+        queue: &Queue,
+        object: &dyn WidgetRender,
+        render_pipeline: Rc<RenderPipeline>,
+    ) -> Self {
+        let vertex_buffer = pool.acquire(
+            device,
+            queue,
+            BufferUsages::VERTEX,
+            bytemuck::cast_slice(object.get_vertices()),
+        );
+        let index_buffer = pool.acquire(
+            device,
+            queue,
+            BufferUsages::INDEX,
+            bytemuck::cast_slice(object.get_indices()),
+        );
+        Self {
+            vertex_buffer,
+            index_buffer,
+            _vertex_len: object.get_vertices().len() as u32,
+            index_len: object.get_indices().len() as u32,
+            render_pipeline,
+            shadow: None,
+        }
+    }
+    fn draw<'a>(&'a self, render_pass: &mut RenderPass<'a>) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint16);
+        render_pass.draw_indexed(0..self.index_len, 0, 0..1)
+    }
+    /// Hands the buffers of this mesh and its shadow back to the pool.
+    fn recycle(self, pool: &mut BufferPool) {
+        if let Some(shadow) = self.shadow {
+            shadow.recycle(pool);
+        }
+        pool.release(self.vertex_buffer);
+        pool.release(self.index_buffer);
+    }
+}
+
+impl Frame {
+    fn new(
+        device: &Device,
+        format: TextureFormat,
+        size: PhysicalSize<u32>,
+        blit: Option<&Blit>,
+    ) -> Self {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Frame"),
+            size: Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::COPY_SRC
+                | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+        let blit_group = blit.map(|blit| {
+            device.create_bind_group(&BindGroupDescriptor {
+                label: Some("Frame Bind Group"),
+                layout: &blit.layout,
+                entries: &[BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                }],
+            })
+        });
+        Self {
+            texture,
+            view,
+            blit_group,
+        }
+    }
+}
+
+impl Blit {
+    fn new(device: &Device, format: TextureFormat, premultiply: bool) -> Self {
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Frame Bind Group Layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: false },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Blit"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader/blit.wgsl"))),
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Blit"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: if premultiply {
+                    "fs_premultiply"
+                } else {
+                    "fs_main"
+                },
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+        Self { pipeline, layout }
+    }
+}
+
+/// Settings for one editor window, see `App::open_window`.
+pub struct WindowConfig {
+    pub title: String,
+    pub size: LogicalSize<u32>,
+    pub widgets: Vec<Box<dyn WidgetRender>>,
+    /// Subscriptions the window starts with.
+    pub events: EventBus,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: "Notes".to_string(),
+            size: LogicalSize::new(600, 300),
+            widgets: vec![],
+            events: EventBus::default(),
+        }
+    }
+}
+
+/// A window's renderer along with the input state of its tools.
+/// `App::run` drives these from its own event loop; to embed the editor
+/// in another one, create them with `new`, forward each window's events
+/// to `handle_event` and redraws to `render_once`, and call `flush_input`
+/// once all events of a frame are in.
+pub struct WindowState {
+    renderer: AppRender,
+    tool: Tool,
+    ink: InkState,
+    eraser: EraserState,
+    cursor: Point,
+    // Latest pointer move not handled yet, see flush_input
+    moved: Option<Point>,
+    modifiers: ModifiersState,
+    pointer: PointerCapture,
+    clicks: ClickCounter,
+    theme: Theme,
+    theme_preference: ThemePreference,
+    last_frame: Instant,
+}
+
+impl WindowState {
+    /// Opens a window as `window_config` describes.
+    pub async fn new<T>(
+        window_target: &EventLoopWindowTarget<T>,
+        window_config: WindowConfig,
+        app_config: &AppConfig,
+    ) -> Result<Self> {
+        let window = WindowBuilder::new()
+            .with_title(window_config.title)
+            .with_min_inner_size(window_config.size)
+            .with_inner_size(window_config.size)
+            .with_resizable(false)
+            .with_transparent(app_config.transparent)
+            .build(window_target)?;
+        let theme = app_config.theme.resolve(window.theme());
+        // Set up before attaching so the first clear is already themed
+        let mut renderer = AppRender::new_offline(window.inner_size());
+        renderer.set_background(theme.background);
+        renderer.set_content_margin(app_config.content_margin);
+        renderer.add_widgets(window_config.widgets);
+        renderer.events().merge(window_config.events);
+        renderer.attach(window, app_config).await?;
+        let mut ink = InkState::default();
+        ink.set_color(theme.ink);
+        Ok(Self {
+            renderer,
+            tool: Tool::default(),
+            ink,
+            eraser: EraserState::default(),
+            cursor: [0.0, 0.0],
+            moved: None,
+            modifiers: ModifiersState::empty(),
+            pointer: PointerCapture::default(),
+            clicks: ClickCounter::new(app_config.multi_click_time, app_config.multi_click_distance),
+            theme,
+            theme_preference: app_config.theme,
+            last_frame: Instant::now(),
+        })
+    }
+    fn apply_theme(&mut self, theme: Theme) {
+        let old = std::mem::replace(&mut self.theme, theme);
+        self.renderer.set_background(theme.background);
+        self.ink.set_color(theme.ink);
+        // Strokes still in the old ink color took it from the theme
+        ink::recolor_strokes(&mut self.renderer, old.ink, theme.ink);
+    }
+    pub fn get_renderer(&self) -> &AppRender {
+        &self.renderer
+    }
+    pub fn get_renderer_mut(&mut self) -> &mut AppRender {
+        &mut self.renderer
+    }
+    /// Whether `render_once` would draw anything new, see
+    /// `AppRender::needs_redraw`.
+    pub fn needs_redraw(&self) -> bool {
+        self.renderer.needs_redraw()
+    }
+    /// Advances animations, delivers queued events and draws a frame.
+    /// A lost surface is reconfigured, to be drawn to on the next call.
+    pub fn render_once(&mut self) -> Result<(), SurfaceError> {
+        let now = Instant::now();
+        // Idle windows do not redraw, so the first frame after a
+        // pause would otherwise jump animations ahead
+        let dt = (now - self.last_frame).min(MAX_FRAME_TIME);
+        self.last_frame = now;
+        let renderer = &mut self.renderer;
+        renderer.update(dt).expect("Update supposed to succeed!");
+        match renderer.render() {
+            Err(SurfaceError::Lost) => {
+                renderer.resize(renderer.size);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+    /// Handles input, resizes and theme changes for this window.
+    pub fn handle_event(&mut self, event: WindowEvent) {
+        match event {
+            WindowEvent::Resized(new_size) => {
+                self.renderer.resize(new_size);
+            }
+            WindowEvent::ScaleFactorChanged {
+                new_inner_size,
+                scale_factor,
+            } => {
+                self.renderer.set_scale_factor(scale_factor);
+                self.renderer.resize(*new_inner_size);
+            }
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers,
+            // The release would go to whichever window gets focus
+            WindowEvent::Focused(false) => self.release(self.cursor),
+            WindowEvent::ThemeChanged(system) if self.theme_preference.follows_system() => {
+                self.apply_theme(self.theme_preference.resolve(Some(system)));
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => match key {
+                VirtualKeyCode::D => self.tool = Tool::Ink,
+                VirtualKeyCode::E => self.tool = Tool::Eraser,
+                VirtualKeyCode::Escape => self.tool = Tool::Pointer,
+                _ => {}
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = self.renderer.physical_to_percent(position);
+                self.move_to(self.cursor);
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => match state {
+                ElementState::Pressed => self.press(self.cursor),
+                ElementState::Released => self.release(self.cursor),
+            },
+            WindowEvent::Touch(Touch {
+                phase, location, ..
+            }) => {
+                let point = self.renderer.physical_to_percent(location);
+                match phase {
+                    TouchPhase::Started => self.press(point),
+                    TouchPhase::Moved => self.move_to(point),
+                    TouchPhase::Ended | TouchPhase::Cancelled => self.release(point),
+                }
+            }
+            _ => {}
+        }
+    }
+    /// Handles the latest pointer move since the last call, moves in
+    /// between only add their points to the ink stroke. Call once per
+    /// frame, before `needs_redraw`.
+    pub fn flush_input(&mut self) {
+        let renderer = &mut self.renderer;
+        if let Some(point) = self.moved.take() {
+            self.eraser.extend(renderer, point);
+            self.pointer.extend(renderer, point);
+        }
+        self.ink.flush(renderer);
+    }
+    // Mice polling faster than the frame rate send many moves per frame
+    fn move_to(&mut self, point: Point) {
+        self.ink.push(point);
+        self.moved = Some(point);
+    }
+    fn press(&mut self, point: Point) {
+        self.flush_input();
+        let clicks = self.clicks.press(Instant::now(), point);
+        let renderer = &mut self.renderer;
+        match self.tool {
+            Tool::Ink => self.ink.begin(renderer, point),
+            Tool::Eraser => self.eraser.begin(renderer, point),
+            Tool::Pointer => {
+                self.pointer.begin(renderer, point);
+                self.pointer.click(renderer, point, clicks);
+            }
+        }
+    }
+    fn release(&mut self, point: Point) {
+        self.flush_input();
+        let renderer = &mut self.renderer;
+        self.ink.finish(renderer);
+        self.eraser.finish();
+        self.pointer.finish(renderer, point);
+    }
 }
 
+pub type OnResize = Box<dyn FnMut(PhysicalSize<u32>)>;
+
 pub struct App {
-    widgets: Vec<Box<dyn WidgetRender>>,
+    windows: Vec<WindowConfig>,
+    config: AppConfig,
+    on_resize: Option<OnResize>,
 }
 
 impl App {
     pub fn new(widgets: Vec<Box<dyn WidgetRender>>) -> Self {
-        Self { widgets }
+        Self::with_config(widgets, AppConfig::default())
     }
-    pub async fn run(&self) -> Result<()> {
+    pub fn with_config(widgets: Vec<Box<dyn WidgetRender>>, config: AppConfig) -> Self {
+        let mut app = Self {
+            windows: vec![],
+            config,
+            on_resize: None,
+        };
+        app.open_window(WindowConfig {
+            widgets,
+            ..Default::default()
+        });
+        app
+    }
+    /// Queues another window to be opened when the app runs.
+    /// While running, Ctrl+N opens an empty one.
+    pub fn open_window(&mut self, window_config: WindowConfig) {
+        self.windows.push(window_config);
+    }
+    /// Calls `callback` whenever a window is resized, with the size its
+    /// renderer ended up with. Minimizing does not change that size.
+    pub fn on_resize(&mut self, callback: impl FnMut(PhysicalSize<u32>) + 'static) {
+        self.on_resize = Some(Box::new(callback));
+    }
+    pub async fn run(self) -> Result<()> {
         let event_loop = EventLoop::new();
-        let window = Window::new(&event_loop)?;
-        let inner_size = LogicalSize::new(600, 300);
-        window.set_min_inner_size(Some(inner_size));
-        window.set_inner_size(inner_size);
-        window.set_resizable(false);
-        let mut renderer = AppRender::new(window).await?;
-        for i in &self.widgets {
-            App::register_object(&mut renderer, i.as_ref());
-        }
-        event_loop.run(move |event, _window_target: _, control_flow| match event {
-            Event::WindowEvent { window_id, event } if window_id == renderer.get_window().id() => {
+        let mut windows = HashMap::new();
+        for i in self.windows {
+            let window = WindowState::new(&event_loop, i, &self.config).await?;
+            windows.insert(window.renderer.get_window().id(), window);
+        }
+        let app_config = self.config;
+        let mut on_resize = self.on_resize;
+        event_loop.run(move |event, window_target, control_flow| match event {
+            Event::WindowEvent { window_id, event } => {
+                let Some(window) = windows.get_mut(&window_id) else {
+                    return;
+                };
                 match event {
-                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                    WindowEvent::Resized(new_size) => {
-                        renderer.resize(new_size);
+                    WindowEvent::CloseRequested => {
+                        windows.remove(&window_id);
+                        if windows.is_empty() {
+                            *control_flow = ControlFlow::Exit;
+                        }
                     }
-                    WindowEvent::ScaleFactorChanged {
-                        new_inner_size,
-                        scale_factor: _,
-                    } => {
-                        renderer.resize(*new_inner_size);
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::N),
+                                ..
+                            },
+                        ..
+                    } if window.modifiers.ctrl() => {
+                        // Window creation is async, but we are inside the event loop
+                        let window = tokio::task::block_in_place(|| {
+                            Handle::current().block_on(WindowState::new(
+                                window_target,
+                                WindowConfig::default(),
+                                &app_config,
+                            ))
+                        });
+                        match window {
+                            Ok(window) => {
+                                windows.insert(window.renderer.get_window().id(), window);
+                            }
+                            Err(x) => eprintln!("Failed to open window: {:?}", x),
+                        }
+                    }
+                    event => {
+                        let resized = matches!(
+                            event,
+                            WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. }
+                        );
+                        window.handle_event(event);
+                        if let (true, Some(on_resize)) = (resized, &mut on_resize) {
+                            on_resize(window.renderer.size);
+                        }
                     }
-                    _ => {}
                 }
             }
-            Event::RedrawRequested(window_id) if window_id == renderer.get_window().id() => {
-                renderer.update().expect("Update supposed to succeed!");
-                match renderer.render() {
+            Event::RedrawRequested(window_id) => {
+                let Some(window) = windows.get_mut(&window_id) else {
+                    return;
+                };
+                match window.render_once() {
                     Ok(_) => {}
-                    Err(SurfaceError::Lost) => renderer.resize(renderer.size),
                     Err(SurfaceError::OutOfMemory) => *control_flow = ControlFlow::ExitWithCode(-1),
                     Err(x) => eprintln!("{:?}", x),
                 }
             }
             Event::MainEventsCleared => {
-                renderer.get_window().request_redraw();
+                *control_flow = ControlFlow::Wait;
+                for i in windows.values_mut() {
+                    i.flush_input();
+                    if i.needs_redraw() {
+                        i.renderer.get_window().request_redraw();
+                    }
+                }
             }
             _ => {}
         });
     }
-    fn register_object(renderer: &mut AppRender, object: &dyn WidgetRender) {
-        let device = &renderer.device;
-        let vertex_buffer = device.create_buffer_init(&util::BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(object.get_vertices()),
-            usage: BufferUsages::VERTEX,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widgets::path::{LineStyle, PathWidget, PathWidgetDesc};
+
+    fn renderer() -> AppRender {
+        let mut renderer = AppRender::new_offline(PhysicalSize::new(600, 300));
+        for x in [0.0, 10.0, 20.0, 30.0] {
+            renderer.add_widget(
+                SquareWidget::new(SquareWidgetDesc {
+                    width: 5.0,
+                    height: 5.0,
+                    x,
+                    ..Default::default()
+                })
+                .build(),
+            );
+        }
+        renderer
+    }
+
+    fn order(renderer: &AppRender) -> Vec<f32> {
+        renderer.get_widgets().map(|i| i.get_x()).collect()
+    }
+
+    #[test]
+    fn reordering_moves_widgets_in_the_draw_order() {
+        let mut renderer = renderer();
+        assert_eq!(renderer.bring_to_front(1), 3);
+        assert_eq!(order(&renderer), [0.0, 20.0, 30.0, 10.0]);
+        assert_eq!(renderer.send_to_back(2), 0);
+        assert_eq!(order(&renderer), [30.0, 0.0, 20.0, 10.0]);
+        assert_eq!(renderer.raise(1), 2);
+        assert_eq!(order(&renderer), [30.0, 20.0, 0.0, 10.0]);
+        assert_eq!(renderer.lower(1), 0);
+        assert_eq!(order(&renderer), [20.0, 30.0, 0.0, 10.0]);
+    }
+
+    #[test]
+    fn scale_factor_maps_logical_pixels() {
+        let mut renderer = renderer();
+        assert_eq!(renderer.get_scale_factor(), 1.0);
+        assert_eq!(
+            renderer.logical_to_percent(LogicalPosition::new(150.0, 150.0)),
+            [25.0, 50.0]
+        );
+        renderer.set_scale_factor(2.0);
+        assert_eq!(
+            renderer.logical_to_percent(LogicalPosition::new(150.0, 75.0)),
+            [50.0, 50.0]
+        );
+        assert_eq!(renderer.events().pending(), 1);
+        renderer.set_scale_factor(2.0);
+        assert_eq!(renderer.events().pending(), 1);
+    }
+
+    #[test]
+    fn pixel_size_follows_window_scale_and_margin() {
+        let mut renderer = renderer();
+        assert_eq!(
+            renderer.logical_pixel_size(),
+            [100.0 / 600.0, 100.0 / 300.0]
+        );
+        renderer.set_scale_factor(2.0);
+        assert_eq!(
+            renderer.logical_pixel_size(),
+            [100.0 / 300.0, 100.0 / 150.0]
+        );
+        renderer.set_content_margin((0.0, 150.0, 0.0, 0.0));
+        assert_eq!(
+            renderer.logical_pixel_size(),
+            [100.0 / 150.0, 100.0 / 150.0]
+        );
+    }
+
+    #[test]
+    fn resizing_measures_dashes_again() {
+        let mut renderer = AppRender::new_offline(PhysicalSize::new(100, 100));
+        let path = PathWidget::new(PathWidgetDesc {
+            points: vec![[0.0, 50.0], [100.0, 50.0]],
+            width: 1.0,
+            style: LineStyle::Dashed { on: 5.0, off: 5.0 },
+            ..Default::default()
         });
-        let vertex_len = object.get_vertices().len() as u32;
-        let index_buffer = device.create_buffer_init(&util::BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(object.get_indices()),
-            usage: BufferUsages::INDEX,
+        renderer.add_widget(path.build());
+        let dashes =
+            |renderer: &AppRender| renderer.get_widgets().next().unwrap().get_vertices().len() / 4;
+        assert_eq!(dashes(&renderer), 10);
+        renderer.resize(PhysicalSize::new(200, 100));
+        assert_eq!(dashes(&renderer), 20);
+        renderer.set_scale_factor(2.0);
+        assert_eq!(dashes(&renderer), 10);
+    }
+
+    #[test]
+    fn content_margin_insets_widget_space() {
+        let mut renderer = renderer();
+        renderer.set_content_margin((30.0, 60.0, 30.0, 0.0));
+        let area = renderer.content_area();
+        assert_eq!(
+            (area.x, area.y, area.width, area.height),
+            (0.0, 10.0, 90.0, 80.0)
+        );
+        assert_eq!(
+            renderer.physical_to_percent(PhysicalPosition::new(270.0, 150.0)),
+            [50.0, 50.0]
+        );
+        // Margins stay the same number of logical pixels
+        renderer.resize(PhysicalSize::new(1200, 600));
+        renderer.set_scale_factor(2.0);
+        let area = renderer.content_area();
+        assert_eq!(
+            (area.x, area.y, area.width, area.height),
+            (0.0, 10.0, 90.0, 80.0)
+        );
+        renderer.set_content_margin((200.0, 0.0, 200.0, 0.0));
+        assert_eq!(renderer.content_area().height, 0.0);
+    }
+
+    #[test]
+    fn bulk_adds_append_in_order() {
+        let mut renderer = renderer();
+        let squares = [40.0, 50.0].map(|x| {
+            SquareWidget::new(SquareWidgetDesc {
+                x,
+                ..Default::default()
+            })
+            .build() as Box<dyn WidgetRender>
         });
-        let index_len = object.get_indices().len() as u32;
-        let widget = WidgetObject {
-            vertex_buffer,
-            index_buffer,
-            _vertex_len: vertex_len,
-            index_len,
-            render_pipeline: object.get_pipeline(renderer),
+        assert_eq!(renderer.add_widgets(squares), 4..6);
+        assert_eq!(order(&renderer), [0.0, 10.0, 20.0, 30.0, 40.0, 50.0]);
+        assert_eq!(renderer.add_widgets(vec![]), 6..6);
+    }
+
+    #[test]
+    fn transparency_needs_a_compositing_alpha_mode() {
+        use CompositeAlphaMode::*;
+        assert_eq!(alpha_mode(&[Opaque, PreMultiplied], true), PreMultiplied);
+        assert_eq!(
+            alpha_mode(&[PreMultiplied, PostMultiplied], true),
+            PostMultiplied
+        );
+        assert_eq!(alpha_mode(&[Opaque, PreMultiplied], false), Opaque);
+        assert_eq!(alpha_mode(&[Opaque, Inherit], true), Opaque);
+        assert_eq!(alpha_mode(&[Inherit], false), Inherit);
+    }
+
+    #[test]
+    fn frames_fit_the_texture_limit() {
+        let fit = |width, height| {
+            let size = fit_within(PhysicalSize::new(width, height), 8192);
+            (size.width, size.height)
         };
-        renderer.widgets.push(widget);
+        assert_eq!(fit(600, 300), (600, 300));
+        assert_eq!(fit(8192, 8192), (8192, 8192));
+        assert_eq!(fit(16384, 4096), (8192, 2048));
+        assert_eq!(fit(3000, 10000), (2457, 8192));
+        assert_eq!(fit(100_000, 1), (8192, 1));
+    }
+
+    #[test]
+    fn surfaces_keep_the_window_size_where_they_can() {
+        let clamp = |width, height| {
+            let size = clamp_within(PhysicalSize::new(width, height), 8192);
+            (size.width, size.height)
+        };
+        assert_eq!(clamp(600, 300), (600, 300));
+        assert_eq!(clamp(16384, 4096), (8192, 4096));
+        assert_eq!(clamp(0, 100_000), (1, 8192));
+    }
+
+    #[test]
+    #[should_panic(expected = "Widget index 0 out of bounds, there are 0 widgets")]
+    fn reordering_without_widgets_panics_clearly() {
+        AppRender::new_offline(PhysicalSize::new(600, 300)).bring_to_front(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Widget index 0 out of bounds, there are 0 widgets")]
+    fn raising_without_widgets_panics_clearly() {
+        AppRender::new_offline(PhysicalSize::new(600, 300)).raise(0);
+    }
+
+    #[test]
+    fn reordering_stops_at_the_ends() {
+        let mut renderer = renderer();
+        assert_eq!(renderer.raise(3), 3);
+        assert_eq!(renderer.lower(0), 0);
+        assert_eq!(renderer.bring_to_front(3), 3);
+        assert_eq!(renderer.send_to_back(0), 0);
+        assert_eq!(order(&renderer), [0.0, 10.0, 20.0, 30.0]);
+    }
+
+    #[test]
+    fn ids_follow_widgets_around() {
+        let mut renderer = renderer();
+        let [bottom, top] = [renderer.widget_id(0), renderer.widget_id(3)];
+        assert_ne!(bottom, top);
+        renderer.bring_to_front(0);
+        assert_eq!(renderer.index_of(bottom), Some(3));
+        assert_eq!(renderer.index_of(top), Some(2));
+        renderer.remove_widget(3);
+        assert_eq!(renderer.index_of(bottom), None);
+        // Not reused by later widgets
+        let index = renderer.add_widget(SquareWidget::create().build());
+        assert_ne!(renderer.widget_id(index), bottom);
     }
 }
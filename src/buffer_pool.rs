@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use wgpu::{Buffer, BufferDescriptor, BufferUsages, Device, Queue, COPY_BUFFER_ALIGNMENT};
+
+// Free buffers kept per bucket, anything beyond is dropped
+const MAX_FREE_PER_BUCKET: usize = 16;
+
+/// Counts since the pool was created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BufferPoolStats {
+    /// Buffers created on the device.
+    pub allocated: usize,
+    /// Requests served by a previously released buffer.
+    pub reused: usize,
+}
+
+/// Recycles vertex and index buffers between widgets.
+/// Buffers are bucketed by usage and size rounded up to a power of two,
+/// so a released buffer can take any contents that fit its bucket.
+#[derive(Debug, Default)]
+pub struct BufferPool {
+    free: HashMap<(BufferUsages, u64), Vec<Buffer>>,
+    stats: BufferPoolStats,
+}
+
+impl BufferPool {
+    /// A buffer of `usage` holding `contents`, reused from the pool if possible.
+    pub fn acquire(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        usage: BufferUsages,
+        contents: &[u8],
+    ) -> Buffer {
+        let usage = usage | BufferUsages::COPY_DST;
+        let size = bucket_size(contents.len() as u64);
+        let buffer = match self.free.get_mut(&(usage, size)).and_then(Vec::pop) {
+            Some(buffer) => {
+                self.stats.reused += 1;
+                buffer
+            }
+            None => {
+                self.stats.allocated += 1;
+                device.create_buffer(&BufferDescriptor {
+                    label: None,
+                    size,
+                    usage,
+                    mapped_at_creation: false,
+                })
+            }
+        };
+        // Writes have to be a multiple of the copy alignment
+        let padding = contents.len() as u64 % COPY_BUFFER_ALIGNMENT;
+        if padding == 0 {
+            queue.write_buffer(&buffer, 0, contents);
+        } else {
+            let mut padded = contents.to_vec();
+            padded.resize(
+                contents.len() + (COPY_BUFFER_ALIGNMENT - padding) as usize,
+                0,
+            );
+            queue.write_buffer(&buffer, 0, &padded);
+        }
+        buffer
+    }
+    /// Hands a buffer from `acquire` back for reuse.
+    pub fn release(&mut self, buffer: Buffer) {
+        let bucket = self
+            .free
+            .entry((buffer.usage(), buffer.size()))
+            .or_default();
+        if bucket.len() < MAX_FREE_PER_BUCKET {
+            bucket.push(buffer);
+        }
+    }
+    pub fn stats(&self) -> BufferPoolStats {
+        self.stats
+    }
+}
+
+fn bucket_size(len: u64) -> u64 {
+    len.max(COPY_BUFFER_ALIGNMENT).next_power_of_two()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_are_aligned_powers_of_two() {
+        assert_eq!(bucket_size(0), 4);
+        assert_eq!(bucket_size(3), 4);
+        assert_eq!(bucket_size(4), 4);
+        assert_eq!(bucket_size(5), 8);
+        assert_eq!(bucket_size(112), 128);
+        assert_eq!(bucket_size(1024), 1024);
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use crate::{app::AppRender, color::Color, geometry::Point};
+
+/*
+ * Event bus
+ * Widgets and handlers emit named events, handlers subscribe by name.
+ * Events are queued and only delivered on the next AppRender::update, so
+ * nothing runs while a widget is still handling input. Events emitted by
+ * handlers wait for the update after that, which keeps cycles from
+ * spinning within one frame.
+ */
+
+/// Data sent along with an event, kept to small copyable values so
+/// emitting does not allocate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Payload {
+    Empty,
+    Bool(bool),
+    Number(f32),
+    Point(Point),
+    Color(Color),
+    /// Index of a widget in the renderer.
+    Widget(usize),
+}
+
+pub type Handler = Box<dyn FnMut(Payload, &mut AppRender)>;
+
+/// The sending half of the bus, handed to widgets while they handle input.
+#[derive(Debug, Default)]
+pub struct Emitter {
+    queue: Vec<(&'static str, Payload)>,
+}
+
+impl Emitter {
+    pub fn emit(&mut self, name: &'static str, payload: Payload) {
+        self.queue.push((name, payload));
+    }
+}
+
+#[derive(Default)]
+pub struct EventBus {
+    emitter: Emitter,
+    handlers: HashMap<&'static str, Vec<Handler>>,
+}
+
+impl EventBus {
+    /// Calls `handler` with the payload of every `name` event, in the
+    /// order handlers subscribed.
+    pub fn subscribe(
+        &mut self,
+        name: &'static str,
+        handler: impl FnMut(Payload, &mut AppRender) + 'static,
+    ) {
+        self.handlers
+            .entry(name)
+            .or_default()
+            .push(Box::new(handler));
+    }
+    pub fn emit(&mut self, name: &'static str, payload: Payload) {
+        self.emitter.emit(name, payload);
+    }
+    pub fn emitter(&mut self) -> &mut Emitter {
+        &mut self.emitter
+    }
+    /// Number of events waiting for the next dispatch.
+    pub fn pending(&self) -> usize {
+        self.emitter.queue.len()
+    }
+    /// Delivers the queued events, events without subscribers are dropped.
+    pub fn dispatch(&mut self, renderer: &mut AppRender) {
+        // Taken so the queue keeps its allocation between frames
+        let mut queue = std::mem::take(&mut self.emitter.queue);
+        for (name, payload) in queue.drain(..) {
+            for handler in self.handlers.get_mut(name).into_iter().flatten() {
+                handler(payload, renderer);
+            }
+        }
+        self.emitter.queue = queue;
+    }
+    /// Takes over the queued events and subscriptions of `other`.
+    pub fn merge(&mut self, mut other: EventBus) {
+        self.emitter.queue.append(&mut other.emitter.queue);
+        for (name, handlers) in other.handlers {
+            self.handlers.entry(name).or_default().extend(handlers);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, time::Duration};
+
+    use winit::dpi::PhysicalSize;
+
+    use super::*;
+    use crate::widgets::{
+        color_picker::{ColorPickerWidget, ColorPickerWidgetDesc, COLOR_CHANGED},
+        square::{SquareWidget, SquareWidgetDesc},
+        PointerEvent, Widget,
+    };
+
+    fn renderer() -> AppRender {
+        AppRender::new_offline(PhysicalSize::new(600, 300))
+    }
+
+    // What would be uploaded, not just what the widget reports
+    fn vertex_colors(renderer: &AppRender, index: usize) -> Vec<[f32; 4]> {
+        let widget = renderer.get_widgets().nth(index).unwrap();
+        widget.get_vertices().iter().map(|i| i.color).collect()
+    }
+
+    #[test]
+    fn events_wait_for_update() {
+        let mut renderer = renderer();
+        let received = Rc::new(RefCell::new(vec![]));
+        let log = received.clone();
+        renderer
+            .events()
+            .subscribe("moved", move |payload, _| log.borrow_mut().push(payload));
+        renderer.events().emit("moved", Payload::Number(1.0));
+        renderer.events().emit("ignored", Payload::Empty);
+        renderer.events().emit("moved", Payload::Number(2.0));
+        assert!(received.borrow().is_empty());
+        renderer.update(Duration::ZERO).unwrap();
+        assert_eq!(
+            *received.borrow(),
+            [Payload::Number(1.0), Payload::Number(2.0)]
+        );
+        assert_eq!(renderer.events().pending(), 0);
+    }
+
+    #[test]
+    fn handlers_can_edit_the_document() {
+        let mut renderer = renderer();
+        renderer.add_widget(SquareWidget::new(SquareWidgetDesc::default()).build());
+        renderer.events().subscribe("recolor", |payload, renderer| {
+            if let Payload::Color(color) = payload {
+                renderer.edit_widget(0, |i| i.set_color(color.to_array()));
+            }
+        });
+        renderer
+            .events()
+            .emit("recolor", Payload::Color(Color::WHITE));
+        renderer.update(Duration::ZERO).unwrap();
+        assert_eq!(vertex_colors(&renderer, 0), [Color::WHITE.to_array(); 4]);
+    }
+
+    #[test]
+    fn chained_events_arrive_on_the_next_update() {
+        let mut renderer = renderer();
+        let count = Rc::new(RefCell::new(0));
+        let seen = count.clone();
+        renderer.events().subscribe("first", |_, renderer| {
+            renderer.events().emit("second", Payload::Empty);
+        });
+        renderer
+            .events()
+            .subscribe("second", move |_, _| *seen.borrow_mut() += 1);
+        renderer.events().emit("first", Payload::Empty);
+        renderer.update(Duration::ZERO).unwrap();
+        assert_eq!(*count.borrow(), 0);
+        assert_eq!(renderer.events().pending(), 1);
+        renderer.update(Duration::ZERO).unwrap();
+        assert_eq!(*count.borrow(), 1);
+    }
+
+    #[test]
+    fn widgets_emit_while_handling_input() {
+        let mut renderer = renderer();
+        let picker = renderer.add_widget(
+            ColorPickerWidget::new(ColorPickerWidgetDesc {
+                width: 50.0,
+                height: 50.0,
+                color: [1.0, 0.0, 0.0, 1.0],
+                ..Default::default()
+            })
+            .build(),
+        );
+        let square = renderer.add_widget(SquareWidget::new(SquareWidgetDesc::default()).build());
+        renderer
+            .events()
+            .subscribe(COLOR_CHANGED, move |payload, renderer| {
+                if let Payload::Color(color) = payload {
+                    renderer.edit_widget(square, |i| i.set_color(color.to_array()));
+                }
+            });
+        // Top-left of the square is white whatever the hue
+        renderer.send_pointer_event(picker, PointerEvent::Pressed([0.0, 0.0]));
+        assert_eq!(renderer.events().pending(), 1);
+        renderer.update(Duration::ZERO).unwrap();
+        assert_eq!(
+            vertex_colors(&renderer, square),
+            [Color::WHITE.to_array(); 4]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+/// Linear RGBA color with components in 0.0 - 1.0, as passed to the shaders.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::new(0.0, 0.0, 0.0, 1.0);
+    pub const WHITE: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+    /// Opaque color from hue in degrees (wrapped into 0 - 360),
+    /// saturation and value in 0.0 - 1.0.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = value - chroma;
+        Self::new(r + m, g + m, b + m, 1.0)
+    }
+    /// Hue in degrees (0 - 360), saturation and value in 0.0 - 1.0.
+    /// Grays have no hue and report 0.
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let chroma = max - min;
+        let hue = if chroma == 0.0 {
+            0.0
+        } else if max == self.r {
+            60.0 * ((self.g - self.b) / chroma).rem_euclid(6.0)
+        } else if max == self.g {
+            60.0 * ((self.b - self.r) / chroma + 2.0)
+        } else {
+            60.0 * ((self.r - self.g) / chroma + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+        (hue, saturation, max)
+    }
+    /// The color with r, g and b multiplied by its alpha.
+    pub fn premultiplied(self) -> Self {
+        Self::new(self.r * self.a, self.g * self.a, self.b * self.a, self.a)
+    }
+    pub fn to_array(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from([r, g, b, a]: [f32; 4]) -> Self {
+        Self::new(r, g, b, a)
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        color.to_array()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Triple = (f32, f32, f32);
+
+    // (hue, saturation, value) and the matching (r, g, b)
+    const PAIRS: [(Triple, Triple); 11] = [
+        ((0.0, 1.0, 1.0), (1.0, 0.0, 0.0)),
+        ((60.0, 1.0, 1.0), (1.0, 1.0, 0.0)),
+        ((120.0, 1.0, 1.0), (0.0, 1.0, 0.0)),
+        ((180.0, 1.0, 1.0), (0.0, 1.0, 1.0)),
+        ((240.0, 1.0, 1.0), (0.0, 0.0, 1.0)),
+        ((300.0, 1.0, 1.0), (1.0, 0.0, 1.0)),
+        ((30.0, 1.0, 1.0), (1.0, 0.5, 0.0)),
+        ((210.0, 0.5, 0.8), (0.4, 0.6, 0.8)),
+        ((0.0, 0.0, 1.0), (1.0, 1.0, 1.0)),
+        ((0.0, 0.0, 0.5), (0.5, 0.5, 0.5)),
+        ((0.0, 0.0, 0.0), (0.0, 0.0, 0.0)),
+    ];
+
+    fn assert_close(actual: Triple, expected: Triple) {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-5;
+        assert!(
+            close(actual.0, expected.0)
+                && close(actual.1, expected.1)
+                && close(actual.2, expected.2),
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn hsv_to_rgb() {
+        for (hsv, rgb) in PAIRS {
+            let color = Color::from_hsv(hsv.0, hsv.1, hsv.2);
+            assert_close((color.r, color.g, color.b), rgb);
+            assert_eq!(color.a, 1.0);
+        }
+    }
+
+    #[test]
+    fn rgb_to_hsv() {
+        for (hsv, (r, g, b)) in PAIRS {
+            assert_close(Color::new(r, g, b, 1.0).to_hsv(), hsv);
+        }
+    }
+
+    #[test]
+    fn hue_wraps_around() {
+        assert_eq!(
+            Color::from_hsv(360.0, 1.0, 1.0),
+            Color::from_hsv(0.0, 1.0, 1.0)
+        );
+        assert_eq!(
+            Color::from_hsv(-120.0, 1.0, 1.0),
+            Color::from_hsv(240.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        for hue in (0..360).step_by(15) {
+            for saturation in [0.25, 0.5, 1.0] {
+                for value in [0.25, 0.75, 1.0] {
+                    let (h, s, v) = Color::from_hsv(hue as f32, saturation, value).to_hsv();
+                    assert_close((h, s, v), (hue as f32, saturation, value));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn premultiplying_scales_by_alpha() {
+        let color = Color::new(1.0, 0.5, 0.0, 0.5).premultiplied();
+        assert_eq!(color, Color::new(0.5, 0.25, 0.0, 0.5));
+        assert_eq!(Color::WHITE.premultiplied(), Color::WHITE);
+    }
+
+    #[test]
+    fn converts_to_and_from_arrays() {
+        let color = Color::new(0.1, 0.2, 0.3, 0.4);
+        assert_eq!(Color::from(color.to_array()), color);
+        assert_eq!(<[f32; 4]>::from(color), [0.1, 0.2, 0.3, 0.4]);
+    }
+}
@@ -0,0 +1,156 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use wgpu::{Features, Limits};
+
+use crate::{
+    app::Margin,
+    pointer::{MULTI_CLICK_DISTANCE, MULTI_CLICK_TIME},
+    theme::ThemePreference,
+};
+
+/// Startup options for `App` and its renderer.
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    /// Routes wgpu validation errors to stderr instead of wgpu's default
+    /// handler, which panics. wgpu 0.16 enables the backend validation layers
+    /// itself in debug builds, so this mainly changes how errors surface.
+    /// Defaults to on in debug builds; `WGPU_VALIDATION=1`/`0` overrides it.
+    pub validation: bool,
+    /// Device features the app cannot run without, startup fails if the
+    /// adapter lacks any of them.
+    pub required_features: Features,
+    /// Device features requested only if the adapter supports them,
+    /// check `AppRender::get_features` for what was granted.
+    pub optional_features: Features,
+    /// Limits requested for the device, startup fails if the adapter
+    /// cannot provide them.
+    pub limits: Limits,
+    /// Colors of the canvas and new strokes, following the OS by default.
+    pub theme: ThemePreference,
+    /// Lets the desktop show through wherever the canvas is translucent,
+    /// including a background with alpha below 1. Windows stay opaque
+    /// where the platform or surface cannot composite them.
+    pub transparent: bool,
+    /// Inset of the content from the window's edges, see
+    /// `AppRender::set_content_margin`.
+    pub content_margin: Margin,
+    /// Longest pause between clicks of a double or triple click.
+    pub multi_click_time: Duration,
+    /// Farthest, in percent, clicks of a double or triple click may land
+    /// from each other.
+    pub multi_click_distance: f32,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        let validation = match std::env::var("WGPU_VALIDATION").as_deref() {
+            Ok("1" | "true") => true,
+            Ok("0" | "false") => false,
+            _ => cfg!(debug_assertions),
+        };
+        Self {
+            validation,
+            required_features: Features::empty(),
+            optional_features: Features::empty(),
+            limits: Limits::default(),
+            theme: ThemePreference::default(),
+            transparent: false,
+            content_margin: (0.0, 0.0, 0.0, 0.0),
+            multi_click_time: MULTI_CLICK_TIME,
+            multi_click_distance: MULTI_CLICK_DISTANCE,
+        }
+    }
+}
+
+impl AppConfig {
+    /// Features to request from an adapter that supports `supported`.
+    pub fn device_features(&self, supported: Features) -> Result<Features> {
+        let missing = self.required_features - supported;
+        if !missing.is_empty() {
+            bail!("The GPU adapter lacks required features {:?}", missing);
+        }
+        Ok(self.required_features | (self.optional_features & supported))
+    }
+    /// Makes sure an adapter with `supported` limits can provide `limits`.
+    pub fn check_limits(&self, supported: &Limits) -> Result<()> {
+        let mut exceeded = vec![];
+        self.limits
+            .check_limits_with_fail_fn(supported, false, |name, requested, allowed| {
+                exceeded.push(format!("{name} ({requested} > {allowed})"))
+            });
+        if !exceeded.is_empty() {
+            bail!(
+                "The GPU adapter cannot provide the requested limits: {}",
+                exceeded.join(", ")
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(required: Features, optional: Features) -> AppConfig {
+        AppConfig {
+            required_features: required,
+            optional_features: optional,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn optional_features_need_support() {
+        let config = config(
+            Features::empty(),
+            Features::PUSH_CONSTANTS | Features::POLYGON_MODE_LINE,
+        );
+        let granted = config.device_features(Features::POLYGON_MODE_LINE).unwrap();
+        assert_eq!(granted, Features::POLYGON_MODE_LINE);
+    }
+
+    #[test]
+    fn required_features_are_always_requested() {
+        let config = config(Features::PUSH_CONSTANTS, Features::POLYGON_MODE_LINE);
+        let granted = config.device_features(Features::all()).unwrap();
+        assert_eq!(
+            granted,
+            Features::PUSH_CONSTANTS | Features::POLYGON_MODE_LINE
+        );
+    }
+
+    #[test]
+    fn missing_required_features_are_named() {
+        let config = config(
+            Features::PUSH_CONSTANTS | Features::POLYGON_MODE_LINE,
+            Features::empty(),
+        );
+        let error = config
+            .device_features(Features::POLYGON_MODE_LINE)
+            .unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("PUSH_CONSTANTS"), "{message}");
+        assert!(!message.contains("POLYGON_MODE_LINE"), "{message}");
+    }
+
+    #[test]
+    fn exceeded_limits_are_named() {
+        let limits = Limits {
+            max_texture_dimension_2d: 16384,
+            ..Default::default()
+        };
+        let config = AppConfig {
+            limits: limits.clone(),
+            ..Default::default()
+        };
+        assert!(config.check_limits(&limits).is_ok());
+        let message = config
+            .check_limits(&Limits::default())
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("max_texture_dimension_2d"), "{message}");
+        assert!(message.contains("16384 > 8192"), "{message}");
+    }
+}
@@ -0,0 +1,180 @@
+use crate::geometry::{Point, Rect};
+
+/*
+ * Widget space -> NDC
+ * Widget space is percent of the window, (0, 0) top-left, (100, 100) bottom-right.
+ * NDC is (-1, 1) top-left, (1, -1) bottom-right, so y flips.
+ */
+
+pub fn percent_to_ndc([x, y]: Point) -> Point {
+    [(x / 100.0) * 2.0 - 1.0, (y / 100.0) * -2.0 + 1.0]
+}
+
+pub fn ndc_to_percent([x, y]: Point) -> Point {
+    [(x + 1.0) / 2.0 * 100.0, (1.0 - y) / 2.0 * 100.0]
+}
+
+/// Scales a width or height from percent to NDC units.
+pub fn percent_to_ndc_extent(extent: f32) -> f32 {
+    extent * 2.0 / 100.0
+}
+
+/// Maps `point` in widget space into `area`, the part of the window
+/// widgets are laid out in, giving percent of the whole window.
+pub fn percent_into_area([x, y]: Point, area: Rect) -> Point {
+    [
+        area.x + x * area.width / 100.0,
+        area.y + y * area.height / 100.0,
+    ]
+}
+
+/// Inverse of `percent_into_area`, widget space for a point in percent
+/// of the whole window.
+pub fn percent_out_of_area([x, y]: Point, area: Rect) -> Point {
+    [
+        (x - area.x) / area.width * 100.0,
+        (y - area.y) / area.height * 100.0,
+    ]
+}
+
+/// `rect` in widget space mapped into `area`, see `percent_into_area`.
+pub fn rect_into_area(rect: Rect, area: Rect) -> Rect {
+    let [x, y] = percent_into_area([rect.x, rect.y], area);
+    Rect {
+        x,
+        y,
+        width: rect.width * area.width / 100.0,
+        height: rect.height * area.height / 100.0,
+    }
+}
+
+/// Inverse of `rect_into_area`.
+pub fn rect_out_of_area(rect: Rect, area: Rect) -> Rect {
+    let [x, y] = percent_out_of_area([rect.x, rect.y], area);
+    Rect {
+        x,
+        y,
+        width: rect.width / area.width * 100.0,
+        height: rect.height / area.height * 100.0,
+    }
+}
+
+/// Pixels `[x, y, width, height]` of a `width` x `height` target touched
+/// by `rect`, None if that leaves no pixels.
+pub fn percent_to_pixel_rect(rect: Rect, width: u32, height: u32) -> Option<[u32; 4]> {
+    if rect.is_empty() {
+        return None;
+    }
+    let scale = |percent: f32, size: u32| percent * size as f32 / 100.0;
+    let clamp = |pixel: f32, size: u32| (pixel.max(0.0) as u32).min(size);
+    let left = clamp(scale(rect.x, width).floor(), width);
+    let top = clamp(scale(rect.y, height).floor(), height);
+    let right = clamp(scale(rect.right(), width).ceil(), width);
+    let bottom = clamp(scale(rect.bottom(), height).ceil(), height);
+    (right > left && bottom > top).then_some([left, top, right - left, bottom - top])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Point, expected: Point) {
+        assert!(
+            (actual[0] - expected[0]).abs() < 1e-5 && (actual[1] - expected[1]).abs() < 1e-5,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn top_left_maps_to_ndc_corner() {
+        assert_close(percent_to_ndc([0.0, 0.0]), [-1.0, 1.0]);
+    }
+
+    #[test]
+    fn bottom_right_maps_to_ndc_corner() {
+        assert_close(percent_to_ndc([100.0, 100.0]), [1.0, -1.0]);
+    }
+
+    #[test]
+    fn center_maps_to_origin() {
+        assert_close(percent_to_ndc([50.0, 50.0]), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn y_grows_downwards() {
+        assert!(percent_to_ndc([0.0, 75.0])[1] < percent_to_ndc([0.0, 25.0])[1]);
+    }
+
+    #[test]
+    fn full_extent_spans_ndc() {
+        assert_eq!(percent_to_ndc_extent(100.0), 2.0);
+        assert_eq!(percent_to_ndc_extent(0.0), 0.0);
+    }
+
+    #[test]
+    fn round_trips() {
+        for x in [0.0, 12.5, 50.0, 99.0, 100.0, -20.0, 150.0] {
+            for y in [0.0, 33.3, 50.0, 100.0, -5.0] {
+                assert_close(ndc_to_percent(percent_to_ndc([x, y])), [x, y]);
+                assert_close(
+                    percent_to_ndc(ndc_to_percent([x / 50.0, y / 50.0])),
+                    [x / 50.0, y / 50.0],
+                );
+            }
+        }
+    }
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn areas_map_both_ways() {
+        let area = rect(10.0, 20.0, 50.0, 60.0);
+        assert_close(percent_into_area([0.0, 0.0], area), [10.0, 20.0]);
+        assert_close(percent_into_area([100.0, 50.0], area), [60.0, 50.0]);
+        assert_close(percent_out_of_area([35.0, 80.0], area), [50.0, 100.0]);
+        for point in [[0.0, 0.0], [25.0, 75.0], [-10.0, 120.0]] {
+            assert_close(
+                percent_out_of_area(percent_into_area(point, area), area),
+                point,
+            );
+        }
+        let mapped = rect_into_area(rect(50.0, 0.0, 50.0, 100.0), area);
+        assert_eq!(mapped, rect(35.0, 20.0, 25.0, 60.0));
+        assert_eq!(rect_out_of_area(mapped, area), rect(50.0, 0.0, 50.0, 100.0));
+    }
+
+    #[test]
+    fn pixel_rect_covers_partial_pixels() {
+        assert_eq!(
+            percent_to_pixel_rect(rect(10.0, 10.0, 20.0, 20.0), 100, 50),
+            Some([10, 5, 20, 10])
+        );
+        assert_eq!(
+            percent_to_pixel_rect(rect(10.5, 10.5, 1.0, 1.0), 100, 100),
+            Some([10, 10, 2, 2])
+        );
+    }
+
+    #[test]
+    fn pixel_rect_is_clamped_to_the_target() {
+        assert_eq!(
+            percent_to_pixel_rect(rect(-50.0, 90.0, 100.0, 50.0), 64, 32),
+            Some([0, 28, 32, 4])
+        );
+        assert_eq!(
+            percent_to_pixel_rect(rect(100.0, 0.0, 10.0, 10.0), 64, 32),
+            None
+        );
+        assert_eq!(
+            percent_to_pixel_rect(rect(10.0, 10.0, 0.0, 10.0), 64, 32),
+            None
+        );
+    }
+}
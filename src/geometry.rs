@@ -0,0 +1,201 @@
+/*
+ * Geometry helpers shared by widgets and tools.
+ * All points are in widget space (0.0 - 100.0 percent of the window).
+ */
+
+pub type Point = [f32; 2];
+
+/// Axis-aligned rect in widget space, (x, y) being the top-left corner.
+/// Rects are half-open: they contain their top/left edges but not their
+/// bottom/right ones, so rects that only touch do not overlap.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+    pub fn center(&self) -> Point {
+        [self.x + self.width / 2.0, self.y + self.height / 2.0]
+    }
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0.0 || self.height <= 0.0
+    }
+    pub fn contains_point(&self, [x, y]: Point) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+    /// Whether both rects share some area, touching edges do not count.
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.intersection(other).is_some()
+    }
+    /// Overlapping area of both rects, None if they do not overlap.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let rect = Rect {
+            x,
+            y,
+            width: self.right().min(other.right()) - x,
+            height: self.bottom().min(other.bottom()) - y,
+        };
+        (!rect.is_empty()).then_some(rect)
+    }
+    /// Smallest rect containing both `self` and `other`.
+    /// Zero-size rects still count as a point at their position.
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect {
+            x,
+            y,
+            width: self.right().max(other.right()) - x,
+            height: self.bottom().max(other.bottom()) - y,
+        }
+    }
+}
+
+pub fn distance(a: Point, b: Point) -> f32 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt()
+}
+
+/// Shortest distance from `point` to the segment `start`-`end`.
+pub fn point_segment_distance(point: Point, start: Point, end: Point) -> f32 {
+    let dx = end[0] - start[0];
+    let dy = end[1] - start[1];
+    let length_squared = dx * dx + dy * dy;
+    if length_squared == 0.0 {
+        return distance(point, start);
+    }
+    let t = (((point[0] - start[0]) * dx + (point[1] - start[1]) * dy) / length_squared)
+        .clamp(0.0, 1.0);
+    distance(point, [start[0] + t * dx, start[1] + t * dy])
+}
+
+/// Douglas-Peucker simplification: drops points that are closer than
+/// `epsilon` to the line between the points that are kept.
+pub fn simplify(points: &[Point], epsilon: f32) -> Vec<Point> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    // Iterative to keep long strokes from overflowing the stack
+    let mut ranges = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = ranges.pop() {
+        let mut furthest = first;
+        let mut max_distance = 0.0;
+        for i in first + 1..last {
+            let d = point_segment_distance(points[i], points[first], points[last]);
+            if d > max_distance {
+                furthest = i;
+                max_distance = d;
+            }
+        }
+        if max_distance > epsilon {
+            keep[furthest] = true;
+            ranges.push((first, furthest));
+            ranges.push((furthest, last));
+        }
+    }
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| keep.then_some(*point))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn rect(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn contains_point_is_half_open() {
+        let r = rect(10.0, 10.0, 20.0, 10.0);
+        assert!(r.contains_point([10.0, 10.0]));
+        assert!(r.contains_point([29.9, 19.9]));
+        assert!(!r.contains_point([30.0, 15.0]));
+        assert!(!r.contains_point([15.0, 20.0]));
+        assert!(!r.contains_point([9.9, 15.0]));
+    }
+
+    #[test]
+    fn zero_size_contains_nothing() {
+        let r = rect(10.0, 10.0, 0.0, 0.0);
+        assert!(r.is_empty());
+        assert!(!r.contains_point([10.0, 10.0]));
+        assert!(!r.intersects(&rect(0.0, 0.0, 50.0, 50.0)));
+    }
+
+    #[test]
+    fn overlapping_rects_intersect() {
+        let a = rect(0.0, 0.0, 20.0, 20.0);
+        let b = rect(10.0, 5.0, 20.0, 10.0);
+        assert!(a.intersects(&b));
+        assert_eq!(a.intersection(&b), Some(rect(10.0, 5.0, 10.0, 10.0)));
+        assert_eq!(a.intersection(&b), b.intersection(&a));
+    }
+
+    #[test]
+    fn touching_edges_do_not_intersect() {
+        let a = rect(0.0, 0.0, 10.0, 10.0);
+        assert!(!a.intersects(&rect(10.0, 0.0, 10.0, 10.0)));
+        assert!(!a.intersects(&rect(0.0, 10.0, 10.0, 10.0)));
+        assert!(!a.intersects(&rect(10.0, 10.0, 10.0, 10.0)));
+        assert_eq!(a.intersection(&rect(10.0, 0.0, 10.0, 10.0)), None);
+    }
+
+    #[test]
+    fn contained_rect_is_its_own_intersection() {
+        let outer = rect(0.0, 0.0, 100.0, 100.0);
+        let inner = rect(25.0, 25.0, 10.0, 10.0);
+        assert_eq!(outer.intersection(&inner), Some(inner));
+    }
+
+    #[test]
+    fn disjoint_rects_do_not_intersect() {
+        let a = rect(0.0, 0.0, 10.0, 10.0);
+        let b = rect(50.0, 50.0, 10.0, 10.0);
+        assert!(!a.intersects(&b));
+        assert_eq!(a.intersection(&b), None);
+    }
+
+    #[test]
+    fn union_covers_both() {
+        let a = rect(0.0, 0.0, 10.0, 10.0);
+        let b = rect(20.0, 5.0, 10.0, 20.0);
+        assert_eq!(a.union(&b), rect(0.0, 0.0, 30.0, 25.0));
+        assert_eq!(a.union(&b), b.union(&a));
+        assert_eq!(a.union(&a), a);
+    }
+
+    #[test]
+    fn union_with_zero_size_rect_includes_its_position() {
+        let a = rect(10.0, 10.0, 10.0, 10.0);
+        let point = rect(40.0, 0.0, 0.0, 0.0);
+        assert_eq!(a.union(&point), rect(10.0, 0.0, 30.0, 20.0));
+    }
+
+    #[test]
+    fn center() {
+        assert_eq!(rect(10.0, 20.0, 30.0, 40.0).center(), [25.0, 40.0]);
+        assert_eq!(rect(5.0, 5.0, 0.0, 0.0).center(), [5.0, 5.0]);
+    }
+}
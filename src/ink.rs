@@ -0,0 +1,278 @@
+use crate::{
+    app::{AppRender, WidgetId},
+    color::Color,
+    geometry::{self, Point},
+    pipeline::BlendMode,
+    widgets::{
+        path::{LineStyle, PathWidget, PathWidgetDesc, MAX_POINTS},
+        Widget,
+    },
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Tool {
+    #[default]
+    Pointer,
+    Ink,
+    Eraser,
+}
+
+// Constant, pressure-insensitive width in percent of the window
+const STROKE_WIDTH: f32 = 1.0;
+// How far, in percent, a simplified stroke may stray from the drawn one
+const SIMPLIFY_EPSILON: f32 = 0.1;
+// Radius, in percent, around the cursor in which strokes get erased
+const ERASER_RADIUS: f32 = 2.0;
+
+/// The stroke currently being drawn, shown live as a widget while the
+/// button is held. The live stroke is held by id, if it is removed
+/// mid-stroke, say by an event handler, the rest of the stroke is dropped.
+#[derive(Debug)]
+pub struct InkState {
+    points: Vec<Point>,
+    live: Option<WidgetId>,
+    color: Color,
+    // Points were pushed since the live stroke was last uploaded
+    stale: bool,
+}
+
+impl Default for InkState {
+    fn default() -> Self {
+        Self {
+            points: vec![],
+            live: None,
+            color: Color::BLACK,
+            stale: false,
+        }
+    }
+}
+
+impl InkState {
+    /// Color of the strokes drawn from now on.
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+    pub fn get_color(&self) -> Color {
+        self.color
+    }
+    pub fn begin(&mut self, renderer: &mut AppRender, point: Point) {
+        self.finish(renderer);
+        self.points.push(point);
+        let index = renderer.add_widget(self.stroke().build());
+        self.live = Some(renderer.widget_id(index));
+    }
+    pub fn extend(&mut self, renderer: &mut AppRender, point: Point) {
+        self.push(point);
+        self.flush(renderer);
+    }
+    /// Adds `point` to the stroke without showing it yet, so many moves
+    /// can share one upload in `flush`.
+    pub fn push(&mut self, point: Point) {
+        if self.live.is_none()
+            || self.points.last() == Some(&point)
+            || self.points.len() >= MAX_POINTS
+        {
+            return;
+        }
+        self.points.push(point);
+        self.stale = true;
+    }
+    /// Shows the points pushed since the last flush.
+    pub fn flush(&mut self, renderer: &mut AppRender) {
+        let Some(index) = self.live_index(renderer) else {
+            return;
+        };
+        if std::mem::take(&mut self.stale) {
+            renderer.update_widget(index, self.stroke().build());
+        }
+    }
+    /// Simplifies the stroke and leaves it in the document as a regular widget.
+    pub fn finish(&mut self, renderer: &mut AppRender) {
+        let index = self.live_index(renderer);
+        self.live = None;
+        if let Some(index) = index {
+            self.points = geometry::simplify(&self.points, SIMPLIFY_EPSILON);
+            renderer.update_widget(index, self.stroke().build());
+        }
+        self.points.clear();
+        self.stale = false;
+    }
+    fn live_index(&mut self, renderer: &AppRender) -> Option<usize> {
+        let index = renderer.index_of(self.live?);
+        if index.is_none() {
+            self.live = None;
+            self.points.clear();
+            self.stale = false;
+        }
+        index
+    }
+    fn stroke(&self) -> PathWidget {
+        PathWidget::new(PathWidgetDesc {
+            points: self.points.clone(),
+            width: STROKE_WIDTH,
+            color: self.color.to_array(),
+            style: LineStyle::Solid,
+            blend_mode: BlendMode::Replace,
+        })
+    }
+}
+
+/// Removes every erasable widget the cursor passes over while the button is held.
+#[derive(Debug, Default)]
+pub struct EraserState {
+    active: bool,
+}
+
+impl EraserState {
+    pub fn begin(&mut self, renderer: &mut AppRender, point: Point) {
+        self.active = true;
+        self.extend(renderer, point);
+    }
+    pub fn extend(&mut self, renderer: &mut AppRender, point: Point) {
+        if !self.active {
+            return;
+        }
+        let hits: Vec<usize> = renderer
+            .get_widgets()
+            .enumerate()
+            .filter(|(_, i)| i.is_erasable() && i.intersects_circle(point, ERASER_RADIUS))
+            .map(|(index, _)| index)
+            .collect();
+        // Back to front so earlier indices stay valid
+        for index in hits.into_iter().rev() {
+            renderer.remove_widget(index);
+        }
+    }
+    pub fn finish(&mut self) {
+        self.active = false;
+    }
+}
+
+/// Changes every stroke drawn in `from` to `to`.
+pub fn recolor_strokes(renderer: &mut AppRender, from: Color, to: Color) {
+    let strokes: Vec<usize> = renderer
+        .get_widgets()
+        .enumerate()
+        .filter(|(_, i)| i.is_erasable() && i.get_color() == from.to_array())
+        .map(|(index, _)| index)
+        .collect();
+    for index in strokes {
+        renderer.edit_widget(index, |i| i.set_color(to.to_array()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::dpi::PhysicalSize;
+
+    use super::*;
+
+    fn renderer() -> AppRender {
+        AppRender::new_offline(PhysicalSize::new(600, 300))
+    }
+
+    #[test]
+    fn strokes_become_widgets() {
+        let mut renderer = renderer();
+        let mut ink = InkState::default();
+        ink.begin(&mut renderer, [10.0, 10.0]);
+        assert_eq!(renderer.get_widgets().len(), 1);
+        for x in 11..=20 {
+            ink.extend(&mut renderer, [x as f32, 10.0]);
+        }
+        ink.finish(&mut renderer);
+        let stroke = renderer.get_widgets().next().unwrap();
+        assert_eq!(stroke.get_x(), 10.0);
+        assert_eq!(stroke.get_width(), 10.0);
+        assert!(stroke.is_erasable());
+        // The straight line simplifies down to its end points
+        assert_eq!(stroke.get_vertices().len(), 4);
+    }
+
+    #[test]
+    fn pushed_points_show_on_flush() {
+        let mut renderer = renderer();
+        let mut ink = InkState::default();
+        ink.begin(&mut renderer, [10.0, 10.0]);
+        for x in 11..=20 {
+            ink.push([x as f32, 10.0]);
+        }
+        let width = || renderer.get_widgets().next().unwrap().get_width();
+        assert_eq!(width(), 0.0);
+        ink.flush(&mut renderer);
+        let width = renderer.get_widgets().next().unwrap().get_width();
+        assert_eq!(width, 10.0);
+    }
+
+    #[test]
+    fn moving_without_a_stroke_draws_nothing() {
+        let mut renderer = renderer();
+        let mut ink = InkState::default();
+        ink.extend(&mut renderer, [10.0, 10.0]);
+        ink.finish(&mut renderer);
+        assert_eq!(renderer.get_widgets().len(), 0);
+    }
+
+    #[test]
+    fn strokes_survive_widgets_below_them_going() {
+        let mut renderer = renderer();
+        let mut ink = InkState::default();
+        ink.begin(&mut renderer, [10.0, 10.0]);
+        ink.finish(&mut renderer);
+        ink.begin(&mut renderer, [10.0, 50.0]);
+        renderer.remove_widget(0);
+        ink.extend(&mut renderer, [90.0, 50.0]);
+        ink.finish(&mut renderer);
+        assert_eq!(renderer.get_widgets().len(), 1);
+        assert_eq!(renderer.get_widgets().next().unwrap().get_width(), 80.0);
+    }
+
+    #[test]
+    fn removing_the_live_stroke_ends_it() {
+        let mut renderer = renderer();
+        let mut ink = InkState::default();
+        ink.begin(&mut renderer, [10.0, 10.0]);
+        renderer.remove_widget(0);
+        renderer.add_widget(PathWidget::create().build());
+        ink.extend(&mut renderer, [90.0, 10.0]);
+        ink.finish(&mut renderer);
+        // The widget now at the stroke's old index is left alone
+        assert_eq!(renderer.get_widgets().next().unwrap().get_width(), 0.0);
+        ink.begin(&mut renderer, [10.0, 50.0]);
+        ink.finish(&mut renderer);
+        assert_eq!(renderer.get_widgets().nth(1).unwrap().get_y(), 50.0);
+    }
+
+    #[test]
+    fn eraser_removes_only_nearby_strokes() {
+        let mut renderer = renderer();
+        let mut ink = InkState::default();
+        for y in [10.0, 50.0] {
+            ink.begin(&mut renderer, [10.0, y]);
+            ink.extend(&mut renderer, [90.0, y]);
+            ink.finish(&mut renderer);
+        }
+        let mut eraser = EraserState::default();
+        eraser.begin(&mut renderer, [50.0, 11.0]);
+        eraser.finish();
+        assert_eq!(renderer.get_widgets().len(), 1);
+        assert_eq!(renderer.get_widgets().next().unwrap().get_y(), 50.0);
+        // Released, so moving over the other stroke keeps it
+        eraser.extend(&mut renderer, [50.0, 50.0]);
+        assert_eq!(renderer.get_widgets().len(), 1);
+    }
+
+    #[test]
+    fn recoloring_leaves_other_colors_alone() {
+        let mut renderer = renderer();
+        let mut ink = InkState::default();
+        ink.begin(&mut renderer, [10.0, 10.0]);
+        ink.finish(&mut renderer);
+        ink.set_color(Color::new(1.0, 0.0, 0.0, 1.0));
+        ink.begin(&mut renderer, [20.0, 20.0]);
+        ink.finish(&mut renderer);
+        recolor_strokes(&mut renderer, Color::BLACK, Color::WHITE);
+        let colors: Vec<[f32; 4]> = renderer.get_widgets().map(|i| i.get_color()).collect();
+        assert_eq!(colors, [Color::WHITE.to_array(), [1.0, 0.0, 0.0, 1.0]]);
+    }
+}
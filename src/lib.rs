@@ -0,0 +1,15 @@
+pub mod app;
+pub mod buffer_pool;
+pub mod bus;
+pub mod color;
+pub mod config;
+pub mod coords;
+pub mod geometry;
+pub mod ink;
+pub mod pipeline;
+pub mod pointer;
+pub mod record;
+pub mod run;
+pub mod theme;
+pub mod vertex;
+pub mod widgets;
@@ -1,8 +1,4 @@
-mod app;
-mod run;
-mod vertex;
-mod widgets;
-use crate::run::run;
+use notes_editor::run::run;
 
 #[tokio::main]
 async fn main() {
@@ -0,0 +1,95 @@
+use wgpu::{
+    BlendComponent, BlendFactor, BlendOperation, BlendState, PipelineLayoutDescriptor,
+    PrimitiveState, RenderPipeline, RenderPipelineDescriptor,
+};
+
+use crate::{app::AppRender, widgets::WidgetRender};
+
+/// How a widget's fragments combine with what is already drawn below it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Overwrites the destination, ignoring alpha.
+    #[default]
+    Replace,
+    /// Regular "over" compositing by source alpha.
+    AlphaBlend,
+    /// Adds the source on top, for glows and highlights.
+    Additive,
+    /// Multiplies the destination by the source, for shadows and tints.
+    Multiply,
+}
+
+impl BlendMode {
+    pub fn to_blend_state(self) -> BlendState {
+        let over_alpha = BlendComponent {
+            src_factor: BlendFactor::One,
+            dst_factor: BlendFactor::OneMinusSrcAlpha,
+            operation: BlendOperation::Add,
+        };
+        match self {
+            BlendMode::Replace => BlendState::REPLACE,
+            BlendMode::AlphaBlend => BlendState::ALPHA_BLENDING,
+            BlendMode::Additive => BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::SrcAlpha,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                alpha: over_alpha,
+            },
+            BlendMode::Multiply => BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::Dst,
+                    dst_factor: BlendFactor::OneMinusSrcAlpha,
+                    operation: BlendOperation::Add,
+                },
+                alpha: over_alpha,
+            },
+        }
+    }
+}
+
+/// Widgets of the same type and blend mode share one pipeline.
+pub type PipelineKey = (&'static str, BlendMode);
+
+/// Builds the pipeline shared by the vertex-coloured widgets: the widget's
+/// shader with `vs_main`/`fs_main`, its vertex layout and blend mode.
+pub fn create_color_pipeline<W: WidgetRender + ?Sized>(
+    app: &AppRender,
+    widget: &W,
+    primitive: PrimitiveState,
+) -> RenderPipeline {
+    let device = app.get_device();
+    let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[],
+        push_constant_ranges: &[],
+    });
+    let shader = widget.get_shader(app);
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: None,
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[widget.get_vertex_desc()],
+        },
+        primitive,
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: app.get_config().format,
+                blend: Some(widget.get_blend_mode().to_blend_state()),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
+}
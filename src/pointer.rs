@@ -0,0 +1,242 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    app::{AppRender, WidgetId},
+    geometry::{self, Point},
+    widgets::PointerEvent,
+};
+
+/// Longest pause between clicks that still counts them together.
+pub const MULTI_CLICK_TIME: Duration = Duration::from_millis(500);
+/// Farthest, in percent, a click may land from the one before and still
+/// count with it.
+pub const MULTI_CLICK_DISTANCE: f32 = 1.0;
+
+/// Pointer-tool drags. The widget a press lands on captures the pointer:
+/// it gets every move and the release, wherever the cursor goes, even
+/// outside its bounds or the window, until the button is released.
+/// The captor is held by id, so it keeps the capture while widgets below
+/// it are removed or reordered.
+#[derive(Debug, Default)]
+pub struct PointerCapture {
+    captured: Option<WidgetId>,
+}
+
+impl PointerCapture {
+    /// The widget holding the capture, if a drag is going on.
+    pub fn captured(&self) -> Option<WidgetId> {
+        self.captured
+    }
+    pub fn begin(&mut self, renderer: &mut AppRender, point: Point) {
+        self.finish(renderer, point);
+        if let Some(index) = renderer.widget_at(point) {
+            self.captured = Some(renderer.widget_id(index));
+            renderer.send_pointer_event(index, PointerEvent::Pressed(point));
+        }
+    }
+    pub fn extend(&mut self, renderer: &mut AppRender, point: Point) {
+        if let Some(index) = self.target(renderer) {
+            renderer.send_pointer_event(index, PointerEvent::Moved(point));
+        }
+    }
+    pub fn finish(&mut self, renderer: &mut AppRender, point: Point) {
+        if let Some(index) = self.target(renderer) {
+            renderer.send_pointer_event(index, PointerEvent::Released(point));
+        }
+        self.captured = None;
+    }
+    /// Tells the captor a press was the `count`th click in a row, see
+    /// `ClickCounter`. Single clicks need no more than the `Pressed`.
+    pub fn click(&mut self, renderer: &mut AppRender, point: Point, count: u32) {
+        let event = match count {
+            2 => PointerEvent::DoubleClicked(point),
+            3 => PointerEvent::TripleClicked(point),
+            _ => return,
+        };
+        if let Some(index) = self.target(renderer) {
+            renderer.send_pointer_event(index, event);
+        }
+    }
+    fn target(&mut self, renderer: &AppRender) -> Option<usize> {
+        let index = renderer.index_of(self.captured?);
+        // The captor was removed mid-drag
+        if index.is_none() {
+            self.captured = None;
+        }
+        index
+    }
+}
+
+/// Counts presses landing close together in place and time, for double
+/// and triple clicks. A fourth click starts over at one.
+#[derive(Debug)]
+pub struct ClickCounter {
+    time: Duration,
+    distance: f32,
+    last: Option<(Instant, Point)>,
+    count: u32,
+}
+
+impl Default for ClickCounter {
+    fn default() -> Self {
+        Self::new(MULTI_CLICK_TIME, MULTI_CLICK_DISTANCE)
+    }
+}
+
+impl ClickCounter {
+    pub fn new(time: Duration, distance: f32) -> Self {
+        Self {
+            time,
+            distance,
+            last: None,
+            count: 0,
+        }
+    }
+    /// Records a press at `point`, returning how many clicks in a row it
+    /// makes.
+    pub fn press(&mut self, now: Instant, point: Point) -> u32 {
+        let follows = self.last.is_some_and(|(time, last)| {
+            now.saturating_duration_since(time) <= self.time
+                && geometry::distance(point, last) <= self.distance
+        });
+        self.count = if follows && self.count < 3 {
+            self.count + 1
+        } else {
+            1
+        };
+        self.last = Some((now, point));
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::dpi::PhysicalSize;
+
+    use super::*;
+    use crate::widgets::{
+        color_picker::{ColorPickerWidget, ColorPickerWidgetDesc},
+        square::{SquareWidget, SquareWidgetDesc},
+        Widget,
+    };
+
+    // Picker at the top left, its color square spans 0 - 40, and a
+    // square to its right
+    fn renderer() -> AppRender {
+        let mut renderer = AppRender::new_offline(PhysicalSize::new(600, 300));
+        renderer.add_widget(
+            ColorPickerWidget::new(ColorPickerWidgetDesc {
+                width: 50.0,
+                height: 50.0,
+                color: [1.0, 0.0, 0.0, 1.0],
+                ..Default::default()
+            })
+            .build(),
+        );
+        renderer.add_widget(
+            SquareWidget::new(SquareWidgetDesc {
+                width: 40.0,
+                height: 40.0,
+                x: 60.0,
+                ..Default::default()
+            })
+            .build(),
+        );
+        renderer
+    }
+
+    fn picked(renderer: &AppRender) -> [f32; 4] {
+        renderer.get_widgets().next().unwrap().get_color()
+    }
+
+    #[test]
+    fn drags_stay_with_the_captor() {
+        let mut renderer = renderer();
+        let mut capture = PointerCapture::default();
+        capture.begin(&mut renderer, [0.0, 0.0]);
+        assert_eq!(capture.captured(), Some(renderer.widget_id(0)));
+        assert_eq!(picked(&renderer), [1.0, 1.0, 1.0, 1.0]);
+        // Over the other square, then outside the window
+        capture.extend(&mut renderer, [80.0, 20.0]);
+        assert_eq!(capture.captured(), Some(renderer.widget_id(0)));
+        let [r, g, b, _] = picked(&renderer);
+        assert!((r - 0.6).abs() < 1e-5 && g == 0.0 && b == 0.0);
+        capture.extend(&mut renderer, [-30.0, 150.0]);
+        assert_eq!(picked(&renderer), [0.0, 0.0, 0.0, 1.0]);
+        capture.finish(&mut renderer, [-30.0, 150.0]);
+        assert_eq!(capture.captured(), None);
+        capture.extend(&mut renderer, [0.0, 0.0]);
+        assert_eq!(picked(&renderer), [0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn presses_on_empty_space_capture_nothing() {
+        let mut renderer = renderer();
+        let mut capture = PointerCapture::default();
+        capture.begin(&mut renderer, [50.0, 90.0]);
+        assert_eq!(capture.captured(), None);
+        capture.extend(&mut renderer, [10.0, 10.0]);
+        assert_eq!(picked(&renderer), [1.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn quick_clicks_in_place_add_up() {
+        let mut clicks = ClickCounter::default();
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        assert_eq!(clicks.press(at(0), [10.0, 10.0]), 1);
+        assert_eq!(clicks.press(at(200), [10.5, 10.0]), 2);
+        assert_eq!(clicks.press(at(400), [10.5, 10.5]), 3);
+        assert_eq!(clicks.press(at(600), [10.5, 10.5]), 1);
+        assert_eq!(clicks.press(at(800), [10.5, 10.5]), 2);
+    }
+
+    #[test]
+    fn slow_or_distant_clicks_start_over() {
+        let mut clicks = ClickCounter::default();
+        let start = Instant::now();
+        assert_eq!(clicks.press(start, [10.0, 10.0]), 1);
+        let late = start + MULTI_CLICK_TIME + Duration::from_millis(1);
+        assert_eq!(clicks.press(late, [10.0, 10.0]), 1);
+        assert_eq!(clicks.press(late, [20.0, 10.0]), 1);
+        assert_eq!(clicks.press(late, [20.0, 10.0]), 2);
+    }
+
+    #[test]
+    fn removing_the_captor_ends_the_drag() {
+        let mut renderer = renderer();
+        let mut capture = PointerCapture::default();
+        capture.begin(&mut renderer, [80.0, 20.0]);
+        assert_eq!(capture.captured(), Some(renderer.widget_id(1)));
+        renderer.remove_widget(1);
+        capture.extend(&mut renderer, [80.0, 30.0]);
+        assert_eq!(capture.captured(), None);
+    }
+
+    #[test]
+    fn removing_a_lower_widget_keeps_the_drag() {
+        let mut renderer = renderer();
+        renderer.bring_to_front(0);
+        let mut capture = PointerCapture::default();
+        capture.begin(&mut renderer, [0.0, 0.0]);
+        assert_eq!(capture.captured(), Some(renderer.widget_id(1)));
+        renderer.remove_widget(0);
+        capture.extend(&mut renderer, [-30.0, 150.0]);
+        assert_eq!(capture.captured(), Some(renderer.widget_id(0)));
+        assert_eq!(picked(&renderer), [0.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn reordering_keeps_the_drag() {
+        let mut renderer = renderer();
+        let mut capture = PointerCapture::default();
+        capture.begin(&mut renderer, [0.0, 0.0]);
+        renderer.bring_to_front(0);
+        capture.extend(&mut renderer, [-30.0, 150.0]);
+        assert_eq!(capture.captured(), Some(renderer.widget_id(1)));
+        let picker = renderer.get_widgets().nth(1).unwrap();
+        assert_eq!(picker.get_color(), [0.0, 0.0, 0.0, 1.0]);
+        capture.finish(&mut renderer, [-30.0, 150.0]);
+        assert_eq!(capture.captured(), None);
+    }
+}
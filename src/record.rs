@@ -0,0 +1,110 @@
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use wgpu::TextureFormat;
+
+use crate::app::AppRender;
+
+/*
+ * Recording
+ * Frames are stepped with a fixed dt of 1 / fps rather than timed, so
+ * the same document always records the same frames, however long each
+ * capture takes. Frame n shows the document n / fps seconds in.
+ */
+
+/// How long and how smoothly to record, see `record`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Recording {
+    pub duration: Duration,
+    pub fps: u32,
+}
+
+impl Recording {
+    /// Frames to capture, the first one before any time has passed.
+    pub fn frame_count(&self) -> usize {
+        (self.duration.as_secs_f64() * self.fps as f64).round() as usize
+    }
+    /// Time between frames.
+    pub fn step(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.fps as f64)
+    }
+}
+
+/// Captures `recording` from `renderer` into `dir` as numbered PNGs,
+/// `frame_00000.png` onwards, creating `dir` if needed. Returns the
+/// paths written, in order.
+pub fn record(renderer: &mut AppRender, recording: Recording, dir: &Path) -> Result<Vec<PathBuf>> {
+    if recording.fps == 0 {
+        bail!("Recording needs at least one frame per second");
+    }
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let mut paths = vec![];
+    for frame in 0..recording.frame_count() {
+        if frame > 0 {
+            renderer.update(recording.step())?;
+        }
+        let path = dir.join(format!("frame_{frame:05}.png"));
+        write_frame(renderer, &path)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Captures the current frame of `renderer` to a PNG at `path`.
+pub fn write_frame(renderer: &AppRender, path: &Path) -> Result<()> {
+    let mut pixels = renderer.capture()?;
+    let config = renderer.get_config();
+    match config.format {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => {}
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => {
+            for pixel in pixels.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        format => bail!("Cannot write {:?} frames as PNG", format),
+    }
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let size = renderer.get_frame_size();
+    let mut encoder = png::Encoder::new(BufWriter::new(file), size.width, size.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()?
+        .write_image_data(&pixels)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_cover_the_duration() {
+        let recording = |millis, fps| Recording {
+            duration: Duration::from_millis(millis),
+            fps,
+        };
+        assert_eq!(recording(1000, 30).frame_count(), 30);
+        assert_eq!(recording(500, 4).frame_count(), 2);
+        assert_eq!(recording(0, 60).frame_count(), 0);
+        assert_eq!(recording(1000, 4).step(), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn recording_without_frame_rate_fails() {
+        let mut renderer = AppRender::new_offline(winit::dpi::PhysicalSize::new(64, 32));
+        let recording = Recording {
+            duration: Duration::from_secs(1),
+            fps: 0,
+        };
+        let dir = std::env::temp_dir();
+        assert!(record(&mut renderer, recording, &dir).is_err());
+    }
+}
@@ -9,6 +9,7 @@ pub async fn run() -> anyhow::Result<()> {
         x: 0.0,
         y: 0.0,
         color: [1.0, 1.0, 1.0, 1.2],
+        ..Default::default()
     })
     .build();
     let sample_box1 = SquareWidget::new(SquareWidgetDesc {
@@ -17,6 +18,7 @@ pub async fn run() -> anyhow::Result<()> {
         x: 00.0,
         y: 00.0,
         color: [1.0, 0.0, 0.0, 1.0],
+        ..Default::default()
     })
     .build();
     let vectored_widget: Vec<Box<dyn WidgetRender>> = vec![sample_box, sample_box1];
@@ -0,0 +1,42 @@
+// Copies the persistent frame to the surface, one fragment per pixel,
+// stretching it if it is smaller.
+
+@group(0) @binding(0)
+var frame: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // 0 - 1 across the target, top-left first
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // One triangle covering the whole target
+    var x: f32 = f32(index / 2u) * 4.0 - 1.0;
+    var y: f32 = f32(index % 2u) * 4.0 - 1.0;
+    var out: VertexOutput;
+    out.position = vec4<f32>(x, y, 0.0, 1.0);
+    out.uv = vec2<f32>(x + 1.0, 1.0 - y) * 0.5;
+    return out;
+}
+
+// Nearest texel, the frame is smaller than the target when it had to fit
+// the texture limit
+fn load(uv: vec2<f32>) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(frame));
+    let texel = min(vec2<i32>(uv * vec2<f32>(size)), size - 1);
+    return textureLoad(frame, texel, 0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return load(in.uv);
+}
+
+// For surfaces composited with pre-multiplied alpha
+@fragment
+fn fs_premultiply(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = load(in.uv);
+    return vec4<f32>(color.rgb * color.a, color.a);
+}
@@ -0,0 +1,69 @@
+use winit::window::Theme as SystemTheme;
+
+use crate::color::Color;
+
+/// Colors the editor chrome picks for the canvas and new strokes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub background: Color,
+    pub ink: Color,
+}
+
+impl Theme {
+    pub const LIGHT: Theme = Theme {
+        background: Color::WHITE,
+        ink: Color::BLACK,
+    };
+    pub const DARK: Theme = Theme {
+        background: Color::new(0.02, 0.02, 0.025, 1.0),
+        ink: Color::new(0.9, 0.9, 0.9, 1.0),
+    };
+}
+
+/// Which theme windows use, see `AppConfig::theme`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ThemePreference {
+    /// Light or dark as the OS is set, switching along with it.
+    /// Platforms that do not report a theme get the light one.
+    #[default]
+    System,
+    /// The given theme, whatever the OS is set to.
+    Fixed(Theme),
+}
+
+impl ThemePreference {
+    /// The theme to use while the OS reports `system`.
+    pub fn resolve(self, system: Option<SystemTheme>) -> Theme {
+        match (self, system) {
+            (ThemePreference::Fixed(theme), _) => theme,
+            (ThemePreference::System, Some(SystemTheme::Dark)) => Theme::DARK,
+            (ThemePreference::System, _) => Theme::LIGHT,
+        }
+    }
+    /// Whether windows should switch themes along with the OS.
+    pub fn follows_system(self) -> bool {
+        self == ThemePreference::System
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn system_preference_follows_the_os() {
+        let preference = ThemePreference::System;
+        assert_eq!(preference.resolve(Some(SystemTheme::Dark)), Theme::DARK);
+        assert_eq!(preference.resolve(Some(SystemTheme::Light)), Theme::LIGHT);
+        assert_eq!(preference.resolve(None), Theme::LIGHT);
+        assert!(preference.follows_system());
+    }
+
+    #[test]
+    fn fixed_preference_ignores_the_os() {
+        let preference = ThemePreference::Fixed(Theme::DARK);
+        assert_eq!(preference.resolve(Some(SystemTheme::Light)), Theme::DARK);
+        assert_eq!(preference.resolve(None), Theme::DARK);
+        assert!(!preference.follows_system());
+    }
+}
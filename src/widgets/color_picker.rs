@@ -0,0 +1,308 @@
+use std::rc::Rc;
+
+use wgpu::RenderPipeline;
+
+use crate::{
+    app::AppRender,
+    bus::{Emitter, Payload},
+    color::Color,
+    coords::percent_to_ndc,
+    geometry::{Point, Rect},
+    pipeline::{create_color_pipeline, BlendMode},
+    vertex::Vertex,
+};
+
+use super::{PointerEvent, Widget, WidgetRender, WidgetVertex};
+
+/*
+ * Picker layout
+ * Left: saturation/value square, saturation grows to the right
+ * and value grows upwards.
+ * Right: hue strip, 0° at the top to 360° at the bottom.
+ * Both are vertex-colored, the square as a grid since its colors
+ * are bilinear rather than linear across a single quad.
+ */
+
+const GRID: u16 = 8;
+const STRIP_WIDTH: f32 = 0.15;
+const GAP: f32 = 0.05;
+const INDICATOR_SIZE: f32 = 2.0;
+
+/// Emitted with `Payload::Color` whenever the user picks a color.
+pub const COLOR_CHANGED: &str = "color_picker.changed";
+
+pub type OnColorChange = Rc<dyn Fn(Color)>;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ColorPickerWidgetDesc {
+    pub width: f32,
+    pub height: f32,
+    pub x: f32,
+    pub y: f32,
+    pub color: [f32; 4],
+    pub blend_mode: BlendMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Part {
+    Square,
+    Strip,
+}
+
+#[derive(Clone, Default)]
+pub struct ColorPickerWidget {
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+    description: ColorPickerWidgetDesc,
+    // Kept apart from the color so grays remember their hue
+    hsv: (f32, f32, f32),
+    dragging: Option<Part>,
+    on_change: Option<OnColorChange>,
+}
+
+impl ColorPickerWidget {
+    pub fn new(desc: ColorPickerWidgetDesc) -> Self {
+        let mut widget = Self {
+            hsv: Color::from(desc.color).to_hsv(),
+            description: desc,
+            ..Default::default()
+        };
+        widget.tessellate();
+        widget
+    }
+    /// Calls `callback` with the new color whenever the user picks one.
+    pub fn on_change(mut self, callback: impl Fn(Color) + 'static) -> Self {
+        self.on_change = Some(Rc::new(callback));
+        self
+    }
+    fn layout(&self) -> (Rect, Rect) {
+        let desc = &self.description;
+        let strip_width = desc.width * STRIP_WIDTH;
+        let square = Rect {
+            x: desc.x,
+            y: desc.y,
+            width: desc.width - strip_width - desc.width * GAP,
+            height: desc.height,
+        };
+        let strip = Rect {
+            x: desc.x + desc.width - strip_width,
+            y: desc.y,
+            width: strip_width,
+            height: desc.height,
+        };
+        (square, strip)
+    }
+    fn pick(&mut self, part: Part, [x, y]: Point, events: &mut Emitter) {
+        let (square, strip) = self.layout();
+        match part {
+            Part::Square => {
+                self.hsv.1 = ((x - square.x) / square.width).clamp(0.0, 1.0);
+                self.hsv.2 = 1.0 - ((y - square.y) / square.height).clamp(0.0, 1.0);
+            }
+            Part::Strip => {
+                self.hsv.0 = ((y - strip.y) / strip.height).clamp(0.0, 1.0) * 360.0;
+            }
+        }
+        self.tessellate();
+        let color = self.get_color().into();
+        if let Some(on_change) = &self.on_change {
+            on_change(color);
+        }
+        events.emit(COLOR_CHANGED, Payload::Color(color));
+    }
+    fn tessellate(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        let (square, strip) = self.layout();
+        let (hue, saturation, value) = self.hsv;
+        let columns = GRID + 1;
+        for row in 0..=GRID {
+            for column in 0..=GRID {
+                let s = column as f32 / GRID as f32;
+                let t = row as f32 / GRID as f32;
+                self.push_vertex(
+                    [square.x + s * square.width, square.y + t * square.height],
+                    Color::from_hsv(hue, s, 1.0 - t),
+                );
+            }
+        }
+        for row in 0..GRID {
+            for column in 0..GRID {
+                let top_left = row * columns + column;
+                self.push_quad_indices(top_left, top_left + 1, top_left + columns);
+            }
+        }
+        let base = self.vertices.len() as u16;
+        for stop in 0..=6 {
+            let y = strip.y + stop as f32 / 6.0 * strip.height;
+            let color = Color::from_hsv(stop as f32 * 60.0, 1.0, 1.0);
+            self.push_vertex([strip.x, y], color);
+            self.push_vertex([strip.x + strip.width, y], color);
+        }
+        for stop in 0..6 {
+            let top_left = base + stop * 2;
+            self.push_quad_indices(top_left, top_left + 1, top_left + 2);
+        }
+        let center = [
+            square.x + saturation * square.width,
+            square.y + (1.0 - value) * square.height,
+        ];
+        self.push_rect(centered(center, INDICATOR_SIZE), Color::BLACK);
+        self.push_rect(centered(center, INDICATOR_SIZE * 0.6), Color::WHITE);
+        let hue_y = strip.y + hue / 360.0 * strip.height;
+        self.push_rect(
+            Rect {
+                x: strip.x,
+                y: hue_y - INDICATOR_SIZE / 4.0,
+                width: strip.width,
+                height: INDICATOR_SIZE / 2.0,
+            },
+            Color::BLACK,
+        );
+    }
+    fn push_vertex(&mut self, position: Point, color: Color) {
+        let [x, y] = percent_to_ndc(position);
+        self.vertices.push(Vertex {
+            position: [x, y, 0.0],
+            color: [color.r, color.g, color.b, self.description.color[3]],
+        });
+    }
+    /// Two triangles for the quad whose bottom-right vertex follows
+    /// `bottom_left` the way `top_right` follows `top_left`.
+    fn push_quad_indices(&mut self, top_left: u16, top_right: u16, bottom_left: u16) {
+        let bottom_right = bottom_left + (top_right - top_left);
+        self.indices.extend([
+            top_left,
+            bottom_left,
+            top_right,
+            bottom_left,
+            bottom_right,
+            top_right,
+        ]);
+    }
+    fn push_rect(&mut self, rect: Rect, color: Color) {
+        let top_left = self.vertices.len() as u16;
+        self.push_vertex([rect.x, rect.y], color);
+        self.push_vertex([rect.right(), rect.y], color);
+        self.push_vertex([rect.x, rect.bottom()], color);
+        self.push_vertex([rect.right(), rect.bottom()], color);
+        self.push_quad_indices(top_left, top_left + 1, top_left + 2);
+    }
+}
+
+fn centered([x, y]: Point, size: f32) -> Rect {
+    Rect {
+        x: x - size / 2.0,
+        y: y - size / 2.0,
+        width: size,
+        height: size,
+    }
+}
+
+impl Widget for ColorPickerWidget {
+    fn create() -> Self {
+        Self::default()
+    }
+    fn set_color(&mut self, color: [f32; 4]) {
+        self.description.color = color;
+        self.hsv = Color::from(color).to_hsv();
+        self.tessellate();
+    }
+    fn get_color(&self) -> [f32; 4] {
+        let (hue, saturation, value) = self.hsv;
+        let color = Color::from_hsv(hue, saturation, value);
+        [color.r, color.g, color.b, self.description.color[3]]
+    }
+    fn set_x(&mut self, x: f32) {
+        self.description.x = x;
+        self.tessellate();
+    }
+    fn get_x(&self) -> f32 {
+        self.description.x
+    }
+    fn set_y(&mut self, y: f32) {
+        self.description.y = y;
+        self.tessellate();
+    }
+    fn get_y(&self) -> f32 {
+        self.description.y
+    }
+    fn set_width(&mut self, width: f32) {
+        self.description.width = width;
+        self.tessellate();
+    }
+    fn get_width(&self) -> f32 {
+        self.description.width
+    }
+    fn set_height(&mut self, height: f32) {
+        self.description.height = height;
+        self.tessellate();
+    }
+    fn get_height(&self) -> f32 {
+        self.description.height
+    }
+    fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.description.blend_mode = blend_mode;
+    }
+    fn get_blend_mode(&self) -> BlendMode {
+        self.description.blend_mode
+    }
+    fn get_vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+    fn get_indices(&self) -> &[u16] {
+        &self.indices
+    }
+    fn on_pointer(&mut self, event: PointerEvent, events: &mut Emitter) -> bool {
+        match event {
+            PointerEvent::Pressed(point) => {
+                let (square, strip) = self.layout();
+                self.dragging = if square.contains_point(point) {
+                    Some(Part::Square)
+                } else if strip.contains_point(point) {
+                    Some(Part::Strip)
+                } else {
+                    None
+                };
+                let Some(part) = self.dragging else {
+                    return false;
+                };
+                self.pick(part, point, events);
+                true
+            }
+            PointerEvent::Moved(point) => {
+                let Some(part) = self.dragging else {
+                    return false;
+                };
+                self.pick(part, point, events);
+                true
+            }
+            PointerEvent::Released(_) => {
+                self.dragging = None;
+                false
+            }
+            PointerEvent::DoubleClicked(_) | PointerEvent::TripleClicked(_) => false,
+        }
+    }
+    fn build(&self) -> Box<Self>
+    where
+        Self: Sized,
+    {
+        Box::new(self.clone())
+    }
+}
+
+impl WidgetRender for ColorPickerWidget {
+    fn get_pipeline(&self, app: &AppRender) -> RenderPipeline {
+        create_color_pipeline(app, self, wgpu::PrimitiveState::default())
+    }
+    fn get_shader(&self, app: &AppRender) -> Rc<wgpu::ShaderModule> {
+        app.get_shader_module(include_str!("../shader/main.wgsl"))
+    }
+}
+
+impl WidgetVertex for ColorPickerWidget {
+    fn get_vertex_desc(&self) -> wgpu::VertexBufferLayout<'static> {
+        Vertex::desc()
+    }
+}
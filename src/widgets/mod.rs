@@ -1,25 +1,122 @@
+use std::{rc::Rc, time::Duration};
+
 use wgpu::{RenderPipeline, ShaderModule, VertexBufferLayout};
 
-use crate::{vertex::Vertex, app::AppRender};
+use crate::{
+    app::AppRender,
+    bus::Emitter,
+    geometry::{self, Point, Rect},
+    pipeline::BlendMode,
+    vertex::Vertex,
+};
+
+use self::shadow::ShadowDesc;
 
+pub mod color_picker;
+pub mod path;
+pub mod progress_bar;
+pub mod ring;
+pub mod shadow;
+pub mod spinner;
 pub mod square;
 
+/// Pointer input in widget space, sent to the widget a press landed on
+/// until the press is released.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PointerEvent {
+    Pressed(Point),
+    Moved(Point),
+    Released(Point),
+    /// Follows the `Pressed` of the second click in quick succession.
+    DoubleClicked(Point),
+    /// Follows the `Pressed` of the third click in quick succession.
+    TripleClicked(Point),
+}
+
 pub trait Widget {
     fn create() -> Self
     where
         Self: Sized;
     fn set_color(&mut self, color: [f32; 4]);
-    fn get_color(&mut self) -> [f32; 4];
+    fn get_color(&self) -> [f32; 4];
     fn set_x(&mut self, x: f32);
     fn get_x(&self) -> f32;
     fn set_y(&mut self, y: f32);
     fn get_y(&self) -> f32;
     fn set_width(&mut self, width: f32);
-    fn get_width(&mut self) -> f32;
+    fn get_width(&self) -> f32;
     fn set_height(&mut self, height: f32);
-    fn get_height(&mut self) -> f32;
+    fn get_height(&self) -> f32;
+    fn set_blend_mode(&mut self, blend_mode: BlendMode);
+    fn get_blend_mode(&self) -> BlendMode;
+    fn get_bounds(&self) -> Rect {
+        Rect {
+            x: self.get_x(),
+            y: self.get_y(),
+            width: self.get_width(),
+            height: self.get_height(),
+        }
+    }
+    /// Shadow the renderer draws behind the widget's bounds, if any.
+    fn get_shadow(&self) -> Option<ShadowDesc> {
+        None
+    }
     fn get_vertices(&self) -> &[Vertex];
     fn get_indices(&self) -> &[u16];
+    /// Whether the eraser tool may remove this widget.
+    fn is_erasable(&self) -> bool {
+        false
+    }
+    /// Whether `point` lies on the widget, for picking what a press lands
+    /// on. Defaults to the bounding box, shapes can override with their
+    /// outline.
+    fn hit_test(&self, point: Point) -> bool {
+        self.get_bounds().contains_point(point)
+    }
+    /// Whether any part of the widget lies within `radius` of `center`.
+    /// Defaults to the bounding box, shapes can override with their outline.
+    fn intersects_circle(&self, center: Point, radius: f32) -> bool {
+        let nearest = [
+            center[0]
+                .max(self.get_x())
+                .min(self.get_x() + self.get_width()),
+            center[1]
+                .max(self.get_y())
+                .min(self.get_y() + self.get_height()),
+        ];
+        geometry::distance(center, nearest) <= radius
+    }
+    /// Handles pointer input, returning whether the widget changed and
+    /// needs to be uploaded again. Events for other parts of the window
+    /// go through `events`.
+    fn on_pointer(&mut self, _event: PointerEvent, _events: &mut Emitter) -> bool {
+        false
+    }
+    /// Widgets nested in this one, for containers.
+    fn get_children(&self) -> &[Box<dyn WidgetRender>] {
+        &[]
+    }
+    /// Moves the nested widgets out, so containers can drop deep trees
+    /// one level at a time rather than recursively.
+    fn take_children(&mut self) -> Vec<Box<dyn WidgetRender>> {
+        vec![]
+    }
+    /// Advances animations by `dt`, returning whether the widget changed
+    /// and needs to be uploaded again.
+    fn update(&mut self, _dt: Duration) -> bool {
+        false
+    }
+    /// Whether the widget wants `update` called every frame.
+    fn is_animating(&self) -> bool {
+        false
+    }
+    /// Tells the widget how many widget units a logical pixel spans along
+    /// x and y, when it is added and whenever the window's size, scale
+    /// factor or content margin change. Returns whether the widget changed
+    /// and needs to be uploaded again, for widgets measured in pixels.
+    fn on_pixel_size(&mut self, _pixel_size: [f32; 2]) -> bool {
+        false
+    }
     fn build(&self) -> Box<Self>
     where
         Self: Sized;
@@ -30,6 +127,258 @@ pub trait WidgetVertex {
 }
 
 pub trait WidgetRender: Widget + WidgetVertex {
+    /// Identifies the widget type for the pipeline cache.
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
     fn get_pipeline(&self, renderer: &AppRender) -> RenderPipeline;
-    fn get_shader(&self, renderer: &AppRender) -> ShaderModule;
+    /// Usually `AppRender::get_shader_module`, which shares modules
+    /// between all widgets with the same source.
+    fn get_shader(&self, renderer: &AppRender) -> Rc<ShaderModule>;
+}
+
+/// Calls `visit` with every widget under `roots`, parents before their
+/// children, along with its path: the index among its siblings at every
+/// level, starting with the root's. Walks with an explicit stack, so
+/// deep nesting cannot overflow.
+pub fn walk<'a>(
+    roots: impl Iterator<Item = &'a dyn WidgetRender> + 'a,
+    mut visit: impl FnMut(&[usize], &'a dyn WidgetRender),
+) {
+    walk_while(roots, |path, widget| {
+        visit(path, widget);
+        true
+    });
+}
+
+/// Path of the first widget under `roots`, in `walk` order, that
+/// matches `predicate`.
+pub fn find<'a>(
+    roots: impl Iterator<Item = &'a dyn WidgetRender> + 'a,
+    mut predicate: impl FnMut(&dyn WidgetRender) -> bool,
+) -> Option<Vec<usize>> {
+    let mut found = None;
+    walk_while(roots, |path, widget| {
+        if predicate(widget) {
+            found = Some(path.to_vec());
+        }
+        found.is_none()
+    });
+    found
+}
+
+type Level<'a> = (Box<dyn Iterator<Item = &'a dyn WidgetRender> + 'a>, usize);
+
+/// `walk`, stopping as soon as `visit` returns false.
+fn walk_while<'a>(
+    roots: impl Iterator<Item = &'a dyn WidgetRender> + 'a,
+    mut visit: impl FnMut(&[usize], &'a dyn WidgetRender) -> bool,
+) {
+    // Siblings left to visit and the index of the next one, per level
+    let mut levels: Vec<Level<'a>> = vec![(Box::new(roots), 0)];
+    let mut path = vec![];
+    while let Some((siblings, next)) = levels.last_mut() {
+        let Some(widget) = siblings.next() else {
+            levels.pop();
+            path.pop();
+            continue;
+        };
+        path.push(*next);
+        *next += 1;
+        if !visit(&path, widget) {
+            return;
+        }
+        let children = widget.get_children();
+        if children.is_empty() {
+            path.pop();
+        } else {
+            levels.push((Box::new(children.iter().map(|i| i.as_ref())), 0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::{
+        square::{SquareWidget, SquareWidgetDesc},
+        *,
+    };
+
+    /// Container for testing the walk, drawn as its frame. Children are
+    /// shared between builds, as they cannot be cloned.
+    #[derive(Clone)]
+    struct Group {
+        frame: SquareWidget<'static>,
+        children: Rc<Vec<Box<dyn WidgetRender>>>,
+    }
+
+    impl Drop for Group {
+        // Dropping the children in turn would recurse once per level
+        fn drop(&mut self) {
+            let mut stack = self.take_children();
+            while let Some(mut child) = stack.pop() {
+                stack.append(&mut child.take_children());
+            }
+        }
+    }
+
+    impl Widget for Group {
+        fn create() -> Self {
+            Self {
+                frame: SquareWidget::create(),
+                children: Rc::default(),
+            }
+        }
+        fn set_color(&mut self, color: [f32; 4]) {
+            self.frame.set_color(color);
+        }
+        fn get_color(&self) -> [f32; 4] {
+            self.frame.get_color()
+        }
+        fn set_x(&mut self, x: f32) {
+            self.frame.set_x(x);
+        }
+        fn get_x(&self) -> f32 {
+            self.frame.get_x()
+        }
+        fn set_y(&mut self, y: f32) {
+            self.frame.set_y(y);
+        }
+        fn get_y(&self) -> f32 {
+            self.frame.get_y()
+        }
+        fn set_width(&mut self, width: f32) {
+            self.frame.set_width(width);
+        }
+        fn get_width(&self) -> f32 {
+            self.frame.get_width()
+        }
+        fn set_height(&mut self, height: f32) {
+            self.frame.set_height(height);
+        }
+        fn get_height(&self) -> f32 {
+            self.frame.get_height()
+        }
+        fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+            self.frame.set_blend_mode(blend_mode);
+        }
+        fn get_blend_mode(&self) -> BlendMode {
+            self.frame.get_blend_mode()
+        }
+        fn get_vertices(&self) -> &[Vertex] {
+            self.frame.get_vertices()
+        }
+        fn get_indices(&self) -> &[u16] {
+            self.frame.get_indices()
+        }
+        fn get_children(&self) -> &[Box<dyn WidgetRender>] {
+            &self.children
+        }
+        fn take_children(&mut self) -> Vec<Box<dyn WidgetRender>> {
+            // Left to the other builds while they still share them
+            Rc::get_mut(&mut self.children)
+                .map(std::mem::take)
+                .unwrap_or_default()
+        }
+        fn build(&self) -> Box<Self> {
+            Box::new(self.clone())
+        }
+    }
+
+    impl WidgetVertex for Group {
+        fn get_vertex_desc(&self) -> VertexBufferLayout<'static> {
+            self.frame.get_vertex_desc()
+        }
+    }
+
+    impl WidgetRender for Group {
+        fn get_pipeline(&self, renderer: &AppRender) -> RenderPipeline {
+            self.frame.get_pipeline(renderer)
+        }
+        fn get_shader(&self, renderer: &AppRender) -> Rc<ShaderModule> {
+            self.frame.get_shader(renderer)
+        }
+    }
+
+    fn square(x: f32) -> Box<dyn WidgetRender> {
+        SquareWidget::new(SquareWidgetDesc {
+            x,
+            ..Default::default()
+        })
+        .build()
+    }
+
+    fn group(x: f32, children: Vec<Box<dyn WidgetRender>>) -> Box<dyn WidgetRender> {
+        let mut group = Group::create();
+        group.set_x(x);
+        group.children = Rc::new(children);
+        group.build()
+    }
+
+    fn tree() -> Vec<Box<dyn WidgetRender>> {
+        vec![
+            square(1.0),
+            group(
+                2.0,
+                vec![square(3.0), group(4.0, vec![square(5.0)]), square(6.0)],
+            ),
+            square(7.0),
+        ]
+    }
+
+    #[test]
+    fn walks_parents_before_children() {
+        let roots = tree();
+        let mut visited = vec![];
+        walk(roots.iter().map(|i| i.as_ref()), |path, widget| {
+            visited.push((path.to_vec(), widget.get_x()))
+        });
+        assert_eq!(
+            visited,
+            [
+                (vec![0], 1.0),
+                (vec![1], 2.0),
+                (vec![1, 0], 3.0),
+                (vec![1, 1], 4.0),
+                (vec![1, 1, 0], 5.0),
+                (vec![1, 2], 6.0),
+                (vec![2], 7.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_the_first_match() {
+        let roots = tree();
+        let roots = || roots.iter().map(|i| i.as_ref());
+        assert_eq!(find(roots(), |i| i.get_x() > 4.0), Some(vec![1, 1, 0]));
+        assert_eq!(find(roots(), |i| i.get_x() > 10.0), None);
+    }
+
+    #[test]
+    fn deep_nesting_does_not_overflow() {
+        let mut nested = square(0.0);
+        for _ in 0..100_000 {
+            nested = group(1.0, vec![nested]);
+        }
+        let mut deepest = 0;
+        walk(std::iter::once(nested.as_ref()), |path, _| {
+            deepest = deepest.max(path.len())
+        });
+        assert_eq!(deepest, 100_001);
+        // Level by level, see Group
+        drop(nested);
+    }
+
+    #[test]
+    fn builds_share_children() {
+        let mut original = Group::create();
+        original.children = Rc::new(vec![square(3.0)]);
+        let copy = original.build();
+        assert_eq!(original.take_children().len(), 0);
+        drop(original);
+        assert_eq!(copy.get_children()[0].get_x(), 3.0);
+    }
 }
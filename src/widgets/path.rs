@@ -0,0 +1,556 @@
+use std::rc::Rc;
+
+use wgpu::RenderPipeline;
+
+use crate::{
+    app::AppRender,
+    coords::percent_to_ndc,
+    geometry::{self, Point, Rect},
+    pipeline::{create_color_pipeline, BlendMode},
+    vertex::Vertex,
+};
+
+use super::{Widget, WidgetRender, WidgetVertex};
+
+/*
+ * Path mesh
+ * Every point gets two vertices, one on each side of the line,
+ * offset by half the width along the (mitered) normal.
+ * Each segment is then two triangles between neighbouring pairs.
+ * A single point is drawn as a square dot of the stroke's width.
+ * Dashed and dotted paths are split into one such mesh per dash,
+ * measured along the path in logical pixels rather than widget units,
+ * whose scales differ along x and y.
+ */
+
+// Sharp turns would otherwise produce arbitrarily long miters
+const MITER_LIMIT: f32 = 4.0;
+// Two vertices per point must stay addressable by u16 indices
+pub const MAX_POINTS: usize = u16::MAX as usize / 2;
+// Shortest dash or gap in logical pixels, shorter ones would barely
+// advance along the path
+const MIN_DASH: f32 = 0.5;
+// Every dash takes at least four vertices, addressed by u16 indices
+const MAX_DASHES: usize = u16::MAX as usize / 4;
+
+/// Dash lengths are in logical pixels, so the pattern looks the same in
+/// every direction and at every window size and scale factor. The stroke
+/// width stays in widget units.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LineStyle {
+    #[default]
+    Solid,
+    Dashed {
+        on: f32,
+        off: f32,
+    },
+    /// Square dots as long as the stroke is thick, one dot apart.
+    Dotted,
+}
+
+impl LineStyle {
+    /// Whether a stroke of `width` is drawn without gaps, as patterns
+    /// without dashes or gaps would never advance.
+    fn is_solid(&self, width: f32) -> bool {
+        let positive = |i: f32| i > 0.0;
+        match *self {
+            LineStyle::Solid => true,
+            LineStyle::Dashed { on, off } => !(positive(on) && positive(off)),
+            LineStyle::Dotted => !positive(width),
+        }
+    }
+    /// Dash and gap lengths along the segment `from`-`to` of a stroke of
+    /// `width`, all but the width in logical pixels.
+    fn lengths(&self, width: f32, pixel_size: [f32; 2], from: Point, to: Point) -> (f32, f32) {
+        let (on, off) = match *self {
+            LineStyle::Dashed { on, off } => (on, off),
+            _ => {
+                let dot = thickness(width, pixel_size, from, to);
+                (dot, dot)
+            }
+        };
+        (on.max(MIN_DASH), off.max(MIN_DASH))
+    }
+}
+
+/// On-screen thickness in logical pixels of a stroke of `width` along
+/// the segment `from`-`to`, given in logical pixels. The stroke is offset
+/// along its normal in widget space, so this depends on the direction.
+fn thickness(width: f32, [x, y]: [f32; 2], from: Point, to: Point) -> f32 {
+    let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+    let length = (dx * dx + dy * dy).sqrt();
+    // Its length in widget units, where the normal is unit length
+    let widget_length = ((dx * x).powi(2) + (dy * y).powi(2)).sqrt();
+    if length < f32::EPSILON || widget_length < f32::EPSILON {
+        return width / x.max(y);
+    }
+    let across = (dy * dy * y / x + dx * dx * x / y) / length;
+    width * across / widget_length
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PathWidgetDesc {
+    pub points: Vec<Point>,
+    pub width: f32,
+    pub color: [f32; 4],
+    pub style: LineStyle,
+    pub blend_mode: BlendMode,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PathWidget {
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+    description: PathWidgetDesc,
+    // Widget units per logical pixel, see `Widget::on_pixel_size`
+    pixel_size: [f32; 2],
+}
+
+impl PathWidget {
+    pub fn new(desc: PathWidgetDesc) -> Self {
+        let mut widget = Self {
+            description: desc,
+            ..Default::default()
+        };
+        widget.tessellate();
+        widget
+    }
+    pub fn get_points(&self) -> &[Point] {
+        &self.description.points
+    }
+    pub fn get_stroke_width(&self) -> f32 {
+        self.description.width
+    }
+    pub fn get_line_style(&self) -> LineStyle {
+        self.description.style
+    }
+    pub fn set_line_style(&mut self, style: LineStyle) {
+        self.description.style = style;
+        self.tessellate();
+    }
+    fn tessellate(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        let desc = &self.description;
+        let points = &desc.points[..desc.points.len().min(MAX_POINTS)];
+        let runs = if desc.style.is_solid(desc.width) || points.len() < 2 {
+            vec![points.to_vec()]
+        } else {
+            let [x, y] = self.get_pixel_size();
+            let pixels: Vec<Point> = points.iter().map(|i| [i[0] / x, i[1] / y]).collect();
+            let lengths = |from, to| desc.style.lengths(desc.width, [x, y], from, to);
+            let mut runs = dashes(&pixels, lengths);
+            for point in runs.iter_mut().flatten() {
+                *point = [point[0] * x, point[1] * y];
+            }
+            runs
+        };
+        for run in runs {
+            // Drop whatever no longer fits behind u16 indices
+            if self.vertices.len() + run.len().max(2) * 2 > u16::MAX as usize {
+                break;
+            }
+            self.tessellate_run(&run);
+        }
+    }
+    fn tessellate_run(&mut self, points: &[Point]) {
+        let desc = &self.description;
+        let half = desc.width / 2.0;
+        let base = self.vertices.len() as u16;
+        let vertex = |position| {
+            let [x, y] = percent_to_ndc(position);
+            Vertex {
+                position: [x, y, 0.0],
+                color: desc.color,
+            }
+        };
+        match points {
+            [] => {}
+            [[x, y]] => {
+                self.vertices.extend([
+                    vertex([x - half, y - half]),
+                    vertex([x + half, y - half]),
+                    vertex([x - half, y + half]),
+                    vertex([x + half, y + half]),
+                ]);
+                self.indices
+                    .extend([0, 2, 1, 2, 3, 1].map(|index| base + index));
+            }
+            _ => {
+                for (i, point) in points.iter().enumerate() {
+                    let before = normal(points[i.saturating_sub(1)], *point);
+                    let after = normal(*point, points[(i + 1).min(points.len() - 1)]);
+                    // End points only have one neighbouring segment
+                    let before = if i == 0 { after } else { before };
+                    let after = if i == points.len() - 1 { before } else { after };
+                    let miter = [before[0] + after[0], before[1] + after[1]];
+                    let miter_length = (miter[0] * miter[0] + miter[1] * miter[1]).sqrt();
+                    let (miter, scale) = if miter_length < f32::EPSILON {
+                        // The path folds back on itself
+                        (after, half)
+                    } else {
+                        let miter = [miter[0] / miter_length, miter[1] / miter_length];
+                        // A zero-length neighbouring segment has a zero normal,
+                        // the bisector is equally close to both real ones
+                        let cos = (miter[0] * after[0] + miter[1] * after[1])
+                            .max(miter[0] * before[0] + miter[1] * before[1]);
+                        (miter, (half / cos).min(half * MITER_LIMIT))
+                    };
+                    let offset = [miter[0] * scale, miter[1] * scale];
+                    self.vertices.extend([
+                        vertex([point[0] + offset[0], point[1] + offset[1]]),
+                        vertex([point[0] - offset[0], point[1] - offset[1]]),
+                    ]);
+                }
+                for i in 0..points.len() as u16 - 1 {
+                    let left = base + i * 2;
+                    self.indices
+                        .extend([left, left + 1, left + 2, left + 1, left + 3, left + 2]);
+                }
+            }
+        }
+    }
+    /// Widget units per logical pixel the dash pattern is measured with,
+    /// one until the path is added to a renderer.
+    pub fn get_pixel_size(&self) -> [f32; 2] {
+        let [x, y] = self.pixel_size;
+        let valid = |i: f32| i.is_finite() && i > 0.0;
+        if valid(x) && valid(y) {
+            [x, y]
+        } else {
+            [1.0, 1.0]
+        }
+    }
+    fn bounds(&self) -> Rect {
+        self.description
+            .points
+            .iter()
+            .map(|&[x, y]| Rect {
+                x,
+                y,
+                width: 0.0,
+                height: 0.0,
+            })
+            .reduce(|bounds, i| bounds.union(&i))
+            .unwrap_or_default()
+    }
+    fn transform(&mut self, f: impl Fn(Point) -> Point) {
+        for point in &mut self.description.points {
+            *point = f(*point);
+        }
+        self.tessellate();
+    }
+}
+
+/// Splits the path into the pieces drawn by a dash pattern, measured
+/// along the path so dashes carry on around corners. `lengths` gives the
+/// dash and gap lengths along each segment. Stops after `MAX_DASHES`,
+/// which also bounds the splitting when lengths barely advance.
+fn dashes(points: &[Point], lengths: impl Fn(Point, Point) -> (f32, f32)) -> Vec<Vec<Point>> {
+    let mut dashes = vec![];
+    let mut current = vec![points[0]];
+    let mut drawing = true;
+    // What is left of the current dash or gap
+    let mut left = lengths(points[0], points[1]).0;
+    for pair in points.windows(2) {
+        let (mut from, to) = (pair[0], pair[1]);
+        let (on, off) = lengths(from, to);
+        let mut length = geometry::distance(from, to);
+        while length > left {
+            let t = left / length;
+            let split = [
+                from[0] + (to[0] - from[0]) * t,
+                from[1] + (to[1] - from[1]) * t,
+            ];
+            if drawing {
+                current.push(split);
+                dashes.push(std::mem::take(&mut current));
+                if dashes.len() == MAX_DASHES {
+                    return dashes;
+                }
+            } else {
+                current = vec![split];
+            }
+            drawing = !drawing;
+            length -= left;
+            from = split;
+            left = if drawing { on } else { off };
+        }
+        left -= length;
+        if drawing {
+            current.push(to);
+        }
+    }
+    if drawing && current.len() > 1 {
+        dashes.push(current);
+    }
+    dashes
+}
+
+/// Unit normal of the segment `from`-`to`, zero if both points coincide.
+fn normal(from: Point, to: Point) -> Point {
+    let dx = to[0] - from[0];
+    let dy = to[1] - from[1];
+    let length = (dx * dx + dy * dy).sqrt();
+    if length < f32::EPSILON {
+        return [0.0, 0.0];
+    }
+    [-dy / length, dx / length]
+}
+
+impl Widget for PathWidget {
+    fn create() -> Self {
+        Self::default()
+    }
+    fn set_color(&mut self, color: [f32; 4]) {
+        self.description.color = color;
+        self.tessellate();
+    }
+    fn get_color(&self) -> [f32; 4] {
+        self.description.color
+    }
+    fn set_x(&mut self, x: f32) {
+        let offset = x - self.get_x();
+        self.transform(|[px, py]| [px + offset, py]);
+    }
+    fn get_x(&self) -> f32 {
+        self.bounds().x
+    }
+    fn set_y(&mut self, y: f32) {
+        let offset = y - self.get_y();
+        self.transform(|[px, py]| [px, py + offset]);
+    }
+    fn get_y(&self) -> f32 {
+        self.bounds().y
+    }
+    fn set_width(&mut self, width: f32) {
+        let Rect {
+            x, width: current, ..
+        } = self.bounds();
+        if current > 0.0 {
+            let scale = width / current;
+            self.transform(|[px, py]| [x + (px - x) * scale, py]);
+        }
+    }
+    fn get_width(&self) -> f32 {
+        self.bounds().width
+    }
+    fn set_height(&mut self, height: f32) {
+        let Rect {
+            y, height: current, ..
+        } = self.bounds();
+        if current > 0.0 {
+            let scale = height / current;
+            self.transform(|[px, py]| [px, y + (py - y) * scale]);
+        }
+    }
+    fn get_height(&self) -> f32 {
+        self.bounds().height
+    }
+    fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.description.blend_mode = blend_mode;
+    }
+    fn get_blend_mode(&self) -> BlendMode {
+        self.description.blend_mode
+    }
+    fn get_vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+    fn get_indices(&self) -> &[u16] {
+        &self.indices
+    }
+    fn is_erasable(&self) -> bool {
+        true
+    }
+    fn on_pixel_size(&mut self, pixel_size: [f32; 2]) -> bool {
+        if pixel_size == self.pixel_size {
+            return false;
+        }
+        self.pixel_size = pixel_size;
+        let desc = &self.description;
+        // Solid paths are measured in widget units only
+        if desc.style.is_solid(desc.width) {
+            return false;
+        }
+        self.tessellate();
+        true
+    }
+    fn hit_test(&self, point: Point) -> bool {
+        // Gaps between dashes count, so dashed strokes are as easy to hit
+        self.intersects_circle(point, 0.0)
+    }
+    fn intersects_circle(&self, center: Point, radius: f32) -> bool {
+        let reach = radius + self.description.width / 2.0;
+        match self.description.points.as_slice() {
+            [] => false,
+            [point] => geometry::distance(center, *point) <= reach,
+            points => points
+                .windows(2)
+                .any(|i| geometry::point_segment_distance(center, i[0], i[1]) <= reach),
+        }
+    }
+    fn build(&self) -> Box<Self>
+    where
+        Self: Sized,
+    {
+        Box::new(self.clone())
+    }
+}
+
+impl WidgetRender for PathWidget {
+    fn get_pipeline(&self, app: &AppRender) -> RenderPipeline {
+        create_color_pipeline(
+            app,
+            self,
+            wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                // Winding flips with the direction the stroke turns
+                cull_mode: None,
+                ..Default::default()
+            },
+        )
+    }
+    fn get_shader(&self, app: &AppRender) -> Rc<wgpu::ShaderModule> {
+        app.get_shader_module(include_str!("../shader/main.wgsl"))
+    }
+}
+
+impl WidgetVertex for PathWidget {
+    fn get_vertex_desc(&self) -> wgpu::VertexBufferLayout<'static> {
+        Vertex::desc()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coords::ndc_to_percent;
+
+    #[test]
+    fn hits_land_on_the_stroke_only() {
+        let path = PathWidget::new(PathWidgetDesc {
+            points: vec![[0.0, 0.0], [10.0, 10.0]],
+            width: 2.0,
+            ..Default::default()
+        });
+        assert!(path.hit_test([5.0, 5.0]));
+        assert!(path.hit_test([5.0, 5.5]));
+        // Inside the bounding box, but off the diagonal
+        assert!(!path.hit_test([9.0, 1.0]));
+    }
+
+    #[test]
+    fn dashes_follow_the_pattern() {
+        let runs = dashes(&[[0.0, 0.0], [10.0, 0.0]], |_, _| (2.0, 1.0));
+        let starts: Vec<f32> = runs.iter().map(|i| i[0][0]).collect();
+        assert_eq!(starts, [0.0, 3.0, 6.0, 9.0]);
+        assert_eq!(runs[0], [[0.0, 0.0], [2.0, 0.0]]);
+        // The last dash is cut short by the end of the path
+        assert_eq!(runs[3], [[9.0, 0.0], [10.0, 0.0]]);
+    }
+
+    #[test]
+    fn dashes_carry_on_around_corners() {
+        let runs = dashes(&[[0.0, 0.0], [3.0, 0.0], [3.0, 3.0]], |_, _| (4.0, 1.0));
+        assert_eq!(runs[0], [[0.0, 0.0], [3.0, 0.0], [3.0, 1.0]]);
+        assert_eq!(runs[1], [[3.0, 2.0], [3.0, 3.0]]);
+        assert_eq!(runs.len(), 2);
+    }
+
+    #[test]
+    fn ending_in_a_gap_drops_it() {
+        let runs = dashes(&[[0.0, 0.0], [2.5, 0.0]], |_, _| (2.0, 1.0));
+        assert_eq!(runs, [vec![[0.0, 0.0], [2.0, 0.0]]]);
+    }
+
+    #[test]
+    fn degenerate_patterns_are_solid() {
+        assert!(LineStyle::Dashed { on: 0.0, off: 1.0 }.is_solid(1.0));
+        assert!(LineStyle::Dashed {
+            on: 1.0,
+            off: f32::NAN
+        }
+        .is_solid(1.0));
+        assert!(LineStyle::Dotted.is_solid(0.0));
+        assert!(!LineStyle::Dotted.is_solid(0.5));
+    }
+
+    #[test]
+    fn tiny_patterns_end() {
+        let path = |width, style| {
+            PathWidget::new(PathWidgetDesc {
+                points: vec![[0.0, 0.0], [10.0, 0.0], [10.0, 10.0]],
+                width,
+                style,
+                ..Default::default()
+            })
+        };
+        let dashed = path(
+            1.0,
+            LineStyle::Dashed {
+                on: 1e-7,
+                off: 1e-7,
+            },
+        );
+        // Clamped to MIN_DASH, so a dash and a gap per pixel
+        assert_eq!(dashed.get_vertices().len(), 20 * 4);
+        let dotted = path(1e-7, LineStyle::Dotted);
+        assert_eq!(dotted.get_vertices().len(), 20 * 4);
+        // Far more dashes than fit are cut off rather than split forever
+        let runs = dashes(&[[0.0, 0.0], [1e9, 0.0]], |_, _| (1e-7, 1e-7));
+        assert_eq!(runs.len(), MAX_DASHES);
+    }
+
+    #[test]
+    fn patterns_are_measured_in_pixels() {
+        let dash_starts = |end| {
+            let mut path = PathWidget::new(PathWidgetDesc {
+                points: vec![[0.0, 0.0], end],
+                width: 1.0,
+                style: LineStyle::Dashed { on: 5.0, off: 5.0 },
+                ..Default::default()
+            });
+            // Twice as many pixels across as down
+            assert!(path.on_pixel_size([0.5, 1.0]));
+            assert!(!path.on_pixel_size([0.5, 1.0]));
+            path.get_vertices()
+                .chunks(2)
+                .map(|i| {
+                    let [a, b] = [i[0].position, i[1].position];
+                    let middle = ndc_to_percent([(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0]);
+                    middle.map(|i| (i * 100.0).round() / 100.0)
+                })
+                .collect::<Vec<_>>()
+        };
+        // 20 pixels across, but only 10 down
+        assert_eq!(
+            dash_starts([10.0, 0.0]),
+            [[0.0, 0.0], [2.5, 0.0], [5.0, 0.0], [7.5, 0.0]]
+        );
+        assert_eq!(dash_starts([0.0, 10.0]), [[0.0, 0.0], [0.0, 5.0]]);
+    }
+
+    #[test]
+    fn dots_are_as_long_as_the_stroke_is_thick() {
+        let pixel_size = [0.5, 1.0];
+        let across = LineStyle::Dotted.lengths(2.0, pixel_size, [0.0, 0.0], [10.0, 0.0]);
+        let down = LineStyle::Dotted.lengths(2.0, pixel_size, [0.0, 0.0], [0.0, 10.0]);
+        assert_eq!(across, (2.0, 2.0));
+        assert_eq!(down, (4.0, 4.0));
+        let diagonal = thickness(2.0, [1.0, 1.0], [0.0, 0.0], [3.0, 4.0]);
+        assert!((diagonal - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn dashed_mesh_has_a_quad_per_dash() {
+        let path = PathWidget::new(PathWidgetDesc {
+            points: vec![[0.0, 0.0], [10.0, 0.0]],
+            width: 1.0,
+            style: LineStyle::Dashed { on: 2.0, off: 1.0 },
+            ..Default::default()
+        });
+        assert_eq!(path.get_vertices().len(), 4 * 4);
+        assert_eq!(path.get_indices().len(), 4 * 6);
+        assert!(path.get_indices().iter().all(|&i| (i as usize) < 16));
+    }
+}
@@ -0,0 +1,249 @@
+use std::{rc::Rc, time::Duration};
+
+use wgpu::RenderPipeline;
+
+use crate::{
+    app::AppRender,
+    pipeline::{create_color_pipeline, BlendMode},
+    vertex::Vertex,
+};
+
+use super::{
+    square::{SquareWidget, SquareWidgetDesc},
+    Widget, WidgetRender, WidgetVertex,
+};
+
+/*
+ * Progress bar
+ * A track square with a fill square on top. Determinate bars fill from
+ * the left, indeterminate ones sweep a fixed-width fill across the
+ * track, clipped to it, entering on the left and leaving on the right.
+ */
+
+// Both as fractions of the track width
+const SWEEP_WIDTH: f32 = 0.3;
+const SWEEP_SPEED: f32 = 0.8;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProgressBarWidgetDesc {
+    pub width: f32,
+    pub height: f32,
+    pub x: f32,
+    pub y: f32,
+    /// Color of the fill.
+    pub color: [f32; 4],
+    pub track_color: [f32; 4],
+    pub blend_mode: BlendMode,
+    /// Done fraction in 0.0 - 1.0, None while it is not known.
+    pub value: Option<f32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProgressBarWidget {
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+    description: ProgressBarWidgetDesc,
+    // Left edge of the indeterminate sweep, from -SWEEP_WIDTH to 1.0
+    sweep: f32,
+}
+
+impl ProgressBarWidget {
+    pub fn new(desc: ProgressBarWidgetDesc) -> Self {
+        let mut widget = Self {
+            description: desc,
+            sweep: -SWEEP_WIDTH,
+            ..Default::default()
+        };
+        widget.set_progress(desc.value);
+        widget
+    }
+    /// Sets the done fraction, clamped to 0.0 - 1.0, or None to sweep.
+    pub fn set_progress(&mut self, value: Option<f32>) {
+        self.description.value = value.map(|i| if i.is_nan() { 0.0 } else { i.clamp(0.0, 1.0) });
+        self.tessellate();
+    }
+    pub fn get_progress(&self) -> Option<f32> {
+        self.description.value
+    }
+    /// Horizontal span of the fill as fractions of the track width.
+    fn fill(&self) -> (f32, f32) {
+        match self.description.value {
+            Some(value) => (0.0, value),
+            None => (self.sweep.max(0.0), (self.sweep + SWEEP_WIDTH).min(1.0)),
+        }
+    }
+    fn tessellate(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        let desc = self.description;
+        self.push_square(SquareWidgetDesc {
+            width: desc.width,
+            height: desc.height,
+            x: desc.x,
+            y: desc.y,
+            color: desc.track_color,
+            ..Default::default()
+        });
+        let (start, end) = self.fill();
+        if end > start {
+            self.push_square(SquareWidgetDesc {
+                width: (end - start) * desc.width,
+                height: desc.height,
+                x: desc.x + start * desc.width,
+                y: desc.y,
+                color: desc.color,
+                ..Default::default()
+            });
+        }
+    }
+    fn push_square(&mut self, desc: SquareWidgetDesc) {
+        let square = SquareWidget::new(desc);
+        let base = self.vertices.len() as u16;
+        self.vertices.extend_from_slice(square.get_vertices());
+        self.indices
+            .extend(square.get_indices().iter().map(|i| base + i));
+    }
+}
+
+impl Widget for ProgressBarWidget {
+    fn create() -> Self {
+        Self::default()
+    }
+    fn set_color(&mut self, color: [f32; 4]) {
+        self.description.color = color;
+        self.tessellate();
+    }
+    fn get_color(&self) -> [f32; 4] {
+        self.description.color
+    }
+    fn set_x(&mut self, x: f32) {
+        self.description.x = x;
+        self.tessellate();
+    }
+    fn get_x(&self) -> f32 {
+        self.description.x
+    }
+    fn set_y(&mut self, y: f32) {
+        self.description.y = y;
+        self.tessellate();
+    }
+    fn get_y(&self) -> f32 {
+        self.description.y
+    }
+    fn set_width(&mut self, width: f32) {
+        self.description.width = width;
+        self.tessellate();
+    }
+    fn get_width(&self) -> f32 {
+        self.description.width
+    }
+    fn set_height(&mut self, height: f32) {
+        self.description.height = height;
+        self.tessellate();
+    }
+    fn get_height(&self) -> f32 {
+        self.description.height
+    }
+    fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.description.blend_mode = blend_mode;
+    }
+    fn get_blend_mode(&self) -> BlendMode {
+        self.description.blend_mode
+    }
+    fn get_vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+    fn get_indices(&self) -> &[u16] {
+        &self.indices
+    }
+    fn update(&mut self, dt: Duration) -> bool {
+        if !self.is_animating() {
+            return false;
+        }
+        let span = 1.0 + SWEEP_WIDTH;
+        self.sweep = (self.sweep + SWEEP_WIDTH + dt.as_secs_f32() * SWEEP_SPEED).rem_euclid(span)
+            - SWEEP_WIDTH;
+        self.tessellate();
+        true
+    }
+    fn is_animating(&self) -> bool {
+        self.description.value.is_none()
+    }
+    fn build(&self) -> Box<Self>
+    where
+        Self: Sized,
+    {
+        Box::new(self.clone())
+    }
+}
+
+impl WidgetRender for ProgressBarWidget {
+    fn get_pipeline(&self, app: &AppRender) -> RenderPipeline {
+        create_color_pipeline(app, self, wgpu::PrimitiveState::default())
+    }
+    fn get_shader(&self, app: &AppRender) -> Rc<wgpu::ShaderModule> {
+        app.get_shader_module(include_str!("../shader/main.wgsl"))
+    }
+}
+
+impl WidgetVertex for ProgressBarWidget {
+    fn get_vertex_desc(&self) -> wgpu::VertexBufferLayout<'static> {
+        Vertex::desc()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar(value: Option<f32>) -> ProgressBarWidget {
+        ProgressBarWidget::new(ProgressBarWidgetDesc {
+            width: 100.0,
+            height: 10.0,
+            value,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn progress_is_clamped() {
+        let mut bar = bar(Some(1.5));
+        assert_eq!(bar.get_progress(), Some(1.0));
+        bar.set_progress(Some(-1.0));
+        assert_eq!(bar.get_progress(), Some(0.0));
+        bar.set_progress(Some(f32::NAN));
+        assert_eq!(bar.get_progress(), Some(0.0));
+    }
+
+    #[test]
+    fn determinate_bars_fill_from_the_left() {
+        assert_eq!(bar(Some(0.0)).get_vertices().len(), 4);
+        let half = bar(Some(0.5));
+        assert_eq!(half.get_vertices().len(), 8);
+        assert_eq!(half.get_indices()[6..], [4, 6, 5, 6, 7, 5]);
+        assert_eq!(half.fill(), (0.0, 0.5));
+    }
+
+    #[test]
+    fn only_indeterminate_bars_animate() {
+        let mut done = bar(Some(0.5));
+        assert!(!done.is_animating());
+        assert!(!done.update(Duration::from_secs(1)));
+        let mut busy = bar(None);
+        assert!(busy.is_animating());
+        assert_eq!(busy.fill(), (0.0, 0.0));
+        assert!(busy.update(Duration::from_millis(500)));
+        let (start, end) = busy.fill();
+        assert!((start - 0.1).abs() < 1e-5 && (end - 0.4).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sweep_wraps_around() {
+        let mut bar = bar(None);
+        let period = (1.0 + SWEEP_WIDTH) / SWEEP_SPEED;
+        bar.update(Duration::from_secs_f32(period * 3.0));
+        // Back between leaving on the right and entering on the left
+        let (start, end) = bar.fill();
+        assert!(end - start < 1e-4, "{start} - {end}");
+    }
+}
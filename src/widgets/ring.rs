@@ -0,0 +1,324 @@
+use std::{f32::consts::TAU, rc::Rc};
+
+use wgpu::RenderPipeline;
+
+use crate::{
+    app::AppRender,
+    coords::percent_to_ndc,
+    geometry::{Point, Rect},
+    pipeline::{create_color_pipeline, BlendMode},
+    vertex::Vertex,
+};
+
+use super::{Widget, WidgetRender, WidgetVertex};
+
+/*
+ * Ring mesh
+ * The ring is inscribed in its bounds, so it is only circular when the
+ * bounds are square on screen. Its outline is split into segments, each
+ * getting an outer and an inner vertex, with two triangles between
+ * neighbouring pairs. A filled circle is a ring whose inner vertices all
+ * sit at the center.
+ */
+
+// Segments a full turn is split into
+const SEGMENTS: usize = 64;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RingWidgetDesc {
+    pub width: f32,
+    pub height: f32,
+    pub x: f32,
+    pub y: f32,
+    pub color: [f32; 4],
+    pub blend_mode: BlendMode,
+    /// Radius of the hole as a fraction of the outer radius, 0.0 for a
+    /// filled circle.
+    pub inner_radius: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RingWidget {
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+    description: RingWidgetDesc,
+}
+
+impl RingWidget {
+    pub fn new(desc: RingWidgetDesc) -> Self {
+        let mut widget = Self {
+            description: desc,
+            ..Default::default()
+        };
+        widget.set_inner_radius(desc.inner_radius);
+        widget
+    }
+    /// Sets the hole's radius as a fraction of the outer one, clamped to
+    /// 0.0 - 1.0.
+    pub fn set_inner_radius(&mut self, inner_radius: f32) {
+        self.description.inner_radius = if inner_radius.is_nan() {
+            0.0
+        } else {
+            inner_radius.clamp(0.0, 1.0)
+        };
+        self.tessellate();
+    }
+    pub fn get_inner_radius(&self) -> f32 {
+        self.description.inner_radius
+    }
+    fn tessellate(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        let desc = self.description;
+        push_arc(
+            &mut self.vertices,
+            &mut self.indices,
+            Arc {
+                bounds: Rect {
+                    x: desc.x,
+                    y: desc.y,
+                    width: desc.width,
+                    height: desc.height,
+                },
+                inner_radius: desc.inner_radius,
+                start: 0.0,
+                sweep: TAU,
+                color: desc.color,
+            },
+        );
+    }
+}
+
+/// Part of a ring, angles in radians clockwise from the top.
+pub(crate) struct Arc {
+    /// What the full ring is inscribed in.
+    pub bounds: Rect,
+    pub inner_radius: f32,
+    pub start: f32,
+    pub sweep: f32,
+    pub color: [f32; 4],
+}
+
+/// Appends the mesh of `arc`, with as many segments as its share of a
+/// full turn needs.
+pub(crate) fn push_arc(vertices: &mut Vec<Vertex>, indices: &mut Vec<u16>, arc: Arc) {
+    let sweep = arc.sweep.clamp(0.0, TAU);
+    if sweep == 0.0 {
+        return;
+    }
+    let center = arc.bounds.center();
+    let (radius_x, radius_y) = (arc.bounds.width / 2.0, arc.bounds.height / 2.0);
+    let segments = ((sweep / TAU * SEGMENTS as f32).ceil() as usize).max(1);
+    let base = vertices.len() as u16;
+    for i in 0..=segments {
+        let angle = arc.start + sweep * i as f32 / segments as f32;
+        for radius in [1.0, arc.inner_radius] {
+            let position = point_on(center, radius_x * radius, radius_y * radius, angle);
+            let [x, y] = percent_to_ndc(position);
+            vertices.push(Vertex {
+                position: [x, y, 0.0],
+                color: arc.color,
+            });
+        }
+    }
+    for i in 0..segments as u16 {
+        let [outer, inner] = [base + i * 2, base + i * 2 + 1];
+        indices.extend([outer, inner, outer + 2, inner, inner + 2, outer + 2]);
+    }
+}
+
+/// Whether `point` lies between `inner_radius` and the edge of the ring
+/// inscribed in `bounds`.
+pub(crate) fn ring_contains(bounds: Rect, inner_radius: f32, point: Point) -> bool {
+    if bounds.is_empty() {
+        return false;
+    }
+    let [x, y] = bounds.center();
+    // In units of the radius along each axis, the ring is then circular
+    let dx = (point[0] - x) / (bounds.width / 2.0);
+    let dy = (point[1] - y) / (bounds.height / 2.0);
+    let squared = dx * dx + dy * dy;
+    squared <= 1.0 && squared >= inner_radius * inner_radius
+}
+
+fn point_on([x, y]: Point, radius_x: f32, radius_y: f32, angle: f32) -> Point {
+    // Widget space y grows downwards, so this turns clockwise on screen
+    [x + radius_x * angle.sin(), y - radius_y * angle.cos()]
+}
+
+impl Widget for RingWidget {
+    fn create() -> Self {
+        Self::default()
+    }
+    fn set_color(&mut self, color: [f32; 4]) {
+        self.description.color = color;
+        self.tessellate();
+    }
+    fn get_color(&self) -> [f32; 4] {
+        self.description.color
+    }
+    fn set_x(&mut self, x: f32) {
+        self.description.x = x;
+        self.tessellate();
+    }
+    fn get_x(&self) -> f32 {
+        self.description.x
+    }
+    fn set_y(&mut self, y: f32) {
+        self.description.y = y;
+        self.tessellate();
+    }
+    fn get_y(&self) -> f32 {
+        self.description.y
+    }
+    fn set_width(&mut self, width: f32) {
+        self.description.width = width;
+        self.tessellate();
+    }
+    fn get_width(&self) -> f32 {
+        self.description.width
+    }
+    fn set_height(&mut self, height: f32) {
+        self.description.height = height;
+        self.tessellate();
+    }
+    fn get_height(&self) -> f32 {
+        self.description.height
+    }
+    fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.description.blend_mode = blend_mode;
+    }
+    fn get_blend_mode(&self) -> BlendMode {
+        self.description.blend_mode
+    }
+    fn hit_test(&self, point: Point) -> bool {
+        let desc = self.description;
+        ring_contains(self.get_bounds(), desc.inner_radius, point)
+    }
+    fn get_vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+    fn get_indices(&self) -> &[u16] {
+        &self.indices
+    }
+    fn build(&self) -> Box<Self>
+    where
+        Self: Sized,
+    {
+        Box::new(self.clone())
+    }
+}
+
+impl WidgetRender for RingWidget {
+    fn get_pipeline(&self, app: &AppRender) -> RenderPipeline {
+        create_color_pipeline(app, self, wgpu::PrimitiveState::default())
+    }
+    fn get_shader(&self, app: &AppRender) -> Rc<wgpu::ShaderModule> {
+        app.get_shader_module(include_str!("../shader/main.wgsl"))
+    }
+}
+
+impl WidgetVertex for RingWidget {
+    fn get_vertex_desc(&self) -> wgpu::VertexBufferLayout<'static> {
+        Vertex::desc()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{coords::ndc_to_percent, geometry};
+
+    fn ring(inner_radius: f32) -> RingWidget {
+        RingWidget::new(RingWidgetDesc {
+            width: 20.0,
+            height: 20.0,
+            x: 10.0,
+            y: 10.0,
+            inner_radius,
+            ..Default::default()
+        })
+    }
+
+    fn positions(widget: &RingWidget) -> Vec<Point> {
+        widget
+            .get_vertices()
+            .iter()
+            .map(|i| ndc_to_percent([i.position[0], i.position[1]]))
+            .collect()
+    }
+
+    #[test]
+    fn inner_radius_is_clamped() {
+        assert_eq!(ring(2.0).get_inner_radius(), 1.0);
+        assert_eq!(ring(-1.0).get_inner_radius(), 0.0);
+        assert_eq!(ring(f32::NAN).get_inner_radius(), 0.0);
+    }
+
+    #[test]
+    fn full_rings_close() {
+        let ring = ring(0.5);
+        assert_eq!(ring.get_vertices().len(), (SEGMENTS + 1) * 2);
+        assert_eq!(ring.get_indices().len(), SEGMENTS * 6);
+        let positions = positions(&ring);
+        let [first, last] = [positions[0], positions[SEGMENTS * 2]];
+        assert!(geometry::distance(first, last) < 1e-4, "{first:?} {last:?}");
+    }
+
+    #[test]
+    fn vertices_lie_on_both_radii() {
+        let positions = positions(&ring(0.5));
+        for pair in positions.chunks(2) {
+            let outer = geometry::distance(pair[0], [20.0, 20.0]);
+            let inner = geometry::distance(pair[1], [20.0, 20.0]);
+            assert!((outer - 10.0).abs() < 1e-3, "{outer}");
+            assert!((inner - 5.0).abs() < 1e-3, "{inner}");
+        }
+    }
+
+    #[test]
+    fn filled_circles_meet_in_the_center() {
+        let positions = positions(&ring(0.0));
+        assert!(positions
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .all(|i| geometry::distance(*i, [20.0, 20.0]) < 1e-4));
+    }
+
+    #[test]
+    fn hits_land_on_the_ring_only() {
+        let circle = ring(0.0);
+        let ring = ring(0.5);
+        assert!(ring.hit_test([20.0, 11.0]));
+        assert!(ring.hit_test([12.0, 20.0]));
+        // The hole and the bounds' corners
+        assert!(!ring.hit_test([20.0, 20.0]));
+        assert!(!ring.hit_test([11.0, 11.0]));
+        assert!(circle.hit_test([20.0, 20.0]));
+        assert!(!circle.hit_test([11.0, 11.0]));
+    }
+
+    #[test]
+    fn arcs_only_use_the_segments_they_need() {
+        let (mut vertices, mut indices) = (vec![], vec![]);
+        let arc = |sweep| Arc {
+            bounds: Rect {
+                x: 0.0,
+                y: 0.0,
+                width: 10.0,
+                height: 10.0,
+            },
+            inner_radius: 0.5,
+            start: 0.0,
+            sweep,
+            color: [1.0; 4],
+        };
+        push_arc(&mut vertices, &mut indices, arc(TAU / 4.0));
+        assert_eq!(vertices.len(), (SEGMENTS / 4 + 1) * 2);
+        push_arc(&mut vertices, &mut indices, arc(0.0));
+        assert_eq!(vertices.len(), (SEGMENTS / 4 + 1) * 2);
+        push_arc(&mut vertices, &mut indices, arc(1e-3));
+        assert_eq!(indices[indices.len() - 6..], [34, 35, 36, 35, 37, 36]);
+    }
+}
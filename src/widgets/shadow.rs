@@ -0,0 +1,178 @@
+use std::rc::Rc;
+
+use wgpu::RenderPipeline;
+
+use crate::{
+    app::AppRender,
+    coords::percent_to_ndc,
+    geometry::{Point, Rect},
+    pipeline::{create_color_pipeline, BlendMode},
+    vertex::Vertex,
+};
+
+use super::{Widget, WidgetRender, WidgetVertex};
+
+/*
+ * Shadow mesh
+ * 0-3: inner rect at full shadow color
+ * 4-7: outer rect fading to transparent
+ * The blur is the width of the fade straddling the shadow's edge,
+ * interpolating vertex alpha across the ring stands in for a gaussian.
+ */
+
+/// Soft shadow drawn behind a widget, lengths in widget units.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ShadowDesc {
+    pub offset: Point,
+    pub blur: f32,
+    pub color: [f32; 4],
+}
+
+/// Pre-blurred quad for the shadow of a widget's bounds, created by the
+/// renderer for widgets that have a shadow.
+#[derive(Debug, Clone, Default)]
+pub struct ShadowWidget {
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+    bounds: Rect,
+    description: ShadowDesc,
+}
+
+impl ShadowWidget {
+    pub fn new(bounds: Rect, desc: ShadowDesc) -> Self {
+        let mut widget = Self {
+            vertices: vec![],
+            indices: vec![],
+            bounds,
+            description: desc,
+        };
+        widget.tessellate();
+        widget
+    }
+    fn tessellate(&mut self) {
+        let desc = &self.description;
+        let rect = Rect {
+            x: self.bounds.x + desc.offset[0],
+            y: self.bounds.y + desc.offset[1],
+            ..self.bounds
+        };
+        let half = desc.blur.max(0.0) / 2.0;
+        let inner = Rect {
+            x: (rect.x + half).min(rect.center()[0]),
+            y: (rect.y + half).min(rect.center()[1]),
+            width: (rect.width - desc.blur).max(0.0),
+            height: (rect.height - desc.blur).max(0.0),
+        };
+        let outer = Rect {
+            x: rect.x - half,
+            y: rect.y - half,
+            width: rect.width + half * 2.0,
+            height: rect.height + half * 2.0,
+        };
+        let [r, g, b, a] = desc.color;
+        let corners = |rect: Rect, color: [f32; 4]| {
+            [
+                [rect.x, rect.y],
+                [rect.right(), rect.y],
+                [rect.right(), rect.bottom()],
+                [rect.x, rect.bottom()],
+            ]
+            .map(|point| {
+                let [x, y] = percent_to_ndc(point);
+                Vertex {
+                    position: [x, y, 0.0],
+                    color,
+                }
+            })
+        };
+        self.vertices = [corners(inner, [r, g, b, a]), corners(outer, [r, g, b, 0.0])].concat();
+        self.indices = vec![0, 1, 2, 0, 2, 3];
+        // One quad per side, between the inner edge and the outer one
+        for side in 0..4 {
+            let next = (side + 1) % 4;
+            self.indices
+                .extend([side, side + 4, next + 4, side, next + 4, next]);
+        }
+    }
+}
+
+impl Widget for ShadowWidget {
+    fn create() -> Self {
+        Self::default()
+    }
+    fn set_color(&mut self, color: [f32; 4]) {
+        self.description.color = color;
+        self.tessellate();
+    }
+    fn get_color(&self) -> [f32; 4] {
+        self.description.color
+    }
+    fn set_x(&mut self, x: f32) {
+        self.bounds.x = x;
+        self.tessellate();
+    }
+    fn get_x(&self) -> f32 {
+        self.bounds.x
+    }
+    fn set_y(&mut self, y: f32) {
+        self.bounds.y = y;
+        self.tessellate();
+    }
+    fn get_y(&self) -> f32 {
+        self.bounds.y
+    }
+    fn set_width(&mut self, width: f32) {
+        self.bounds.width = width;
+        self.tessellate();
+    }
+    fn get_width(&self) -> f32 {
+        self.bounds.width
+    }
+    fn set_height(&mut self, height: f32) {
+        self.bounds.height = height;
+        self.tessellate();
+    }
+    fn get_height(&self) -> f32 {
+        self.bounds.height
+    }
+    // Shadows always fade into what is below them
+    fn set_blend_mode(&mut self, _blend_mode: BlendMode) {}
+    fn get_blend_mode(&self) -> BlendMode {
+        BlendMode::AlphaBlend
+    }
+    fn get_vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+    fn get_indices(&self) -> &[u16] {
+        &self.indices
+    }
+    fn build(&self) -> Box<Self>
+    where
+        Self: Sized,
+    {
+        Box::new(self.clone())
+    }
+}
+
+impl WidgetRender for ShadowWidget {
+    fn get_pipeline(&self, app: &AppRender) -> RenderPipeline {
+        create_color_pipeline(
+            app,
+            self,
+            wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: None,
+                ..Default::default()
+            },
+        )
+    }
+    fn get_shader(&self, app: &AppRender) -> Rc<wgpu::ShaderModule> {
+        app.get_shader_module(include_str!("../shader/main.wgsl"))
+    }
+}
+
+impl WidgetVertex for ShadowWidget {
+    fn get_vertex_desc(&self) -> wgpu::VertexBufferLayout<'static> {
+        Vertex::desc()
+    }
+}
@@ -0,0 +1,266 @@
+use std::{f32::consts::TAU, rc::Rc, time::Duration};
+
+use wgpu::RenderPipeline;
+
+use crate::{
+    app::AppRender,
+    geometry::{Point, Rect},
+    pipeline::{create_color_pipeline, BlendMode},
+    vertex::Vertex,
+};
+
+use super::{
+    ring::{push_arc, ring_contains, Arc},
+    Widget, WidgetRender, WidgetVertex,
+};
+
+/*
+ * Spinner
+ * An arc of a ring that turns at a steady speed while its sweep grows
+ * and shrinks between MIN_SWEEP and MAX_SWEEP, once per SWEEP_PERIOD.
+ * Stopped spinners keep the arc where it is and stop asking for frames.
+ */
+
+// Turns per second
+const ROTATION_SPEED: f32 = 0.8;
+// Both as fractions of a full turn
+const MIN_SWEEP: f32 = 0.1;
+const MAX_SWEEP: f32 = 0.75;
+const SWEEP_PERIOD: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpinnerWidgetDesc {
+    pub width: f32,
+    pub height: f32,
+    pub x: f32,
+    pub y: f32,
+    pub color: [f32; 4],
+    pub blend_mode: BlendMode,
+    /// Width of the arc as a fraction of the radius, 1.0 reaching the center.
+    pub thickness: f32,
+    pub spinning: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpinnerWidget {
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+    description: SpinnerWidgetDesc,
+    // Seconds spun so far, wrapped to a common period of both motions
+    time: f32,
+}
+
+impl SpinnerWidget {
+    pub fn new(desc: SpinnerWidgetDesc) -> Self {
+        let mut widget = Self {
+            description: desc,
+            ..Default::default()
+        };
+        widget.set_thickness(desc.thickness);
+        widget
+    }
+    /// Sets the arc's width as a fraction of the radius, clamped to
+    /// 0.0 - 1.0.
+    pub fn set_thickness(&mut self, thickness: f32) {
+        self.description.thickness = if thickness.is_nan() {
+            0.0
+        } else {
+            thickness.clamp(0.0, 1.0)
+        };
+        self.tessellate();
+    }
+    pub fn get_thickness(&self) -> f32 {
+        self.description.thickness
+    }
+    pub fn set_spinning(&mut self, spinning: bool) {
+        self.description.spinning = spinning;
+    }
+    pub fn is_spinning(&self) -> bool {
+        self.description.spinning
+    }
+    /// Start and sweep of the arc in radians, clockwise from the top.
+    fn arc(&self) -> (f32, f32) {
+        let wave = 0.5 - 0.5 * (self.time / SWEEP_PERIOD * TAU).cos();
+        let sweep = MIN_SWEEP + (MAX_SWEEP - MIN_SWEEP) * wave;
+        // The tail stays put while the arc grows and catches up as it
+        // shrinks, on top of the steady turn
+        let start = self.time * ROTATION_SPEED + (MAX_SWEEP - sweep);
+        (start.fract() * TAU, sweep * TAU)
+    }
+    fn tessellate(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        let desc = self.description;
+        let (start, sweep) = self.arc();
+        push_arc(
+            &mut self.vertices,
+            &mut self.indices,
+            Arc {
+                bounds: Rect {
+                    x: desc.x,
+                    y: desc.y,
+                    width: desc.width,
+                    height: desc.height,
+                },
+                inner_radius: 1.0 - desc.thickness,
+                start,
+                sweep,
+                color: desc.color,
+            },
+        );
+    }
+}
+
+impl Widget for SpinnerWidget {
+    fn create() -> Self {
+        Self::default()
+    }
+    fn set_color(&mut self, color: [f32; 4]) {
+        self.description.color = color;
+        self.tessellate();
+    }
+    fn get_color(&self) -> [f32; 4] {
+        self.description.color
+    }
+    fn set_x(&mut self, x: f32) {
+        self.description.x = x;
+        self.tessellate();
+    }
+    fn get_x(&self) -> f32 {
+        self.description.x
+    }
+    fn set_y(&mut self, y: f32) {
+        self.description.y = y;
+        self.tessellate();
+    }
+    fn get_y(&self) -> f32 {
+        self.description.y
+    }
+    fn set_width(&mut self, width: f32) {
+        self.description.width = width;
+        self.tessellate();
+    }
+    fn get_width(&self) -> f32 {
+        self.description.width
+    }
+    fn set_height(&mut self, height: f32) {
+        self.description.height = height;
+        self.tessellate();
+    }
+    fn get_height(&self) -> f32 {
+        self.description.height
+    }
+    fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.description.blend_mode = blend_mode;
+    }
+    fn get_blend_mode(&self) -> BlendMode {
+        self.description.blend_mode
+    }
+    fn hit_test(&self, point: Point) -> bool {
+        // The whole ring, as the arc moves too fast to aim at
+        ring_contains(self.get_bounds(), 1.0 - self.description.thickness, point)
+    }
+    fn get_vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+    fn get_indices(&self) -> &[u16] {
+        &self.indices
+    }
+    fn update(&mut self, dt: Duration) -> bool {
+        if !self.is_animating() {
+            return false;
+        }
+        // Both motions repeat after this, and f32 seconds lose precision
+        let period = SWEEP_PERIOD * 4.0 / ROTATION_SPEED;
+        self.time = (self.time + dt.as_secs_f32()).rem_euclid(period);
+        self.tessellate();
+        true
+    }
+    fn is_animating(&self) -> bool {
+        self.description.spinning
+    }
+    fn build(&self) -> Box<Self>
+    where
+        Self: Sized,
+    {
+        Box::new(self.clone())
+    }
+}
+
+impl WidgetRender for SpinnerWidget {
+    fn get_pipeline(&self, app: &AppRender) -> RenderPipeline {
+        create_color_pipeline(app, self, wgpu::PrimitiveState::default())
+    }
+    fn get_shader(&self, app: &AppRender) -> Rc<wgpu::ShaderModule> {
+        app.get_shader_module(include_str!("../shader/main.wgsl"))
+    }
+}
+
+impl WidgetVertex for SpinnerWidget {
+    fn get_vertex_desc(&self) -> wgpu::VertexBufferLayout<'static> {
+        Vertex::desc()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spinner(spinning: bool) -> SpinnerWidget {
+        SpinnerWidget::new(SpinnerWidgetDesc {
+            width: 10.0,
+            height: 10.0,
+            thickness: 0.25,
+            spinning,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn only_spinning_spinners_animate() {
+        let mut stopped = spinner(false);
+        assert!(!stopped.is_animating());
+        assert!(!stopped.update(Duration::from_secs(1)));
+        assert_eq!(stopped.arc(), spinner(false).arc());
+        let mut spinning = spinner(true);
+        assert!(spinning.is_animating());
+        assert!(spinning.update(Duration::from_millis(100)));
+        assert_ne!(spinning.arc(), spinner(true).arc());
+        spinning.set_spinning(false);
+        assert!(!spinning.is_animating());
+    }
+
+    #[test]
+    fn sweep_stays_in_range() {
+        let mut spinner = spinner(true);
+        for _ in 0..200 {
+            spinner.update(Duration::from_millis(16));
+            let (start, sweep) = spinner.arc();
+            assert!((0.0..TAU).contains(&start), "{start}");
+            let range = MIN_SWEEP * TAU - 1e-4..=MAX_SWEEP * TAU + 1e-4;
+            assert!(range.contains(&sweep), "{sweep}");
+        }
+    }
+
+    #[test]
+    fn arc_grows_then_shrinks() {
+        let mut spinner = spinner(true);
+        let (_, first) = spinner.arc();
+        spinner.update(Duration::from_secs_f32(SWEEP_PERIOD / 2.0));
+        let (_, half) = spinner.arc();
+        spinner.update(Duration::from_secs_f32(SWEEP_PERIOD / 2.0));
+        let (_, full) = spinner.arc();
+        assert!((first - MIN_SWEEP * TAU).abs() < 1e-4);
+        assert!((half - MAX_SWEEP * TAU).abs() < 1e-4);
+        assert!((full - first).abs() < 1e-3);
+    }
+
+    #[test]
+    fn thickness_is_clamped() {
+        let mut spinner = spinner(false);
+        spinner.set_thickness(3.0);
+        assert_eq!(spinner.get_thickness(), 1.0);
+        spinner.set_thickness(f32::NAN);
+        assert_eq!(spinner.get_thickness(), 0.0);
+    }
+}
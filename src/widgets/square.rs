@@ -1,10 +1,16 @@
-use std::borrow::Cow;
+use std::rc::Rc;
 
-use wgpu::{PipelineLayoutDescriptor, RenderPipeline, RenderPipelineDescriptor};
+use wgpu::RenderPipeline;
 
-use crate::{app::AppRender, vertex::Vertex};
+use crate::{
+    app::AppRender,
+    coords::{percent_to_ndc, percent_to_ndc_extent},
+    geometry::Rect,
+    pipeline::{create_color_pipeline, BlendMode},
+    vertex::Vertex,
+};
 
-use super::{Widget, WidgetRender, WidgetVertex};
+use super::{shadow::ShadowDesc, Widget, WidgetRender, WidgetVertex};
 
 /*
  * Vertex style-guide
@@ -27,6 +33,19 @@ pub struct SquareWidgetDesc {
     pub x: f32,
     pub y: f32,
     pub color: [f32; 4],
+    pub blend_mode: BlendMode,
+    pub shadow: Option<ShadowDesc>,
+}
+
+impl SquareWidgetDesc {
+    pub fn to_rect(&self) -> Rect {
+        Rect {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -38,36 +57,41 @@ pub struct SquareWidget<'a> {
 
 impl<'a> SquareWidget<'a> {
     pub fn new(desc: SquareWidgetDesc) -> Self {
-        let x = (desc.x / 100.0) * 2.0 - 1.0;
-        let y = (desc.y / 100.0) * -2.0 + 1.0;
-        let width = desc.width * 2.0 / 100.0;
-        let height = desc.height * 2.0 / 100.0;
-        Self {
-            verticies: [
-                Vertex {
-                    // Top Left
-                    position: [x, y, 0.0],
-                    color: desc.color,
-                },
-                Vertex {
-                    // Top Right
-                    position: [x + width, y, 0.0],
-                    color: desc.color,
-                },
-                Vertex {
-                    // Bottom Left
-                    position: [x, y - height, 0.0],
-                    color: desc.color,
-                },
-                Vertex {
-                    // Bottom Right
-                    position: [x + width, y - height, 0.0],
-                    color: desc.color,
-                },
-            ],
-            indicies: &[0, 2, 1, 2, 3, 1],
+        let mut widget = Self {
             description: desc,
-        }
+            ..Default::default()
+        };
+        widget.tessellate();
+        widget
+    }
+    fn tessellate(&mut self) {
+        let desc = self.description;
+        let [x, y] = percent_to_ndc([desc.x, desc.y]);
+        let width = percent_to_ndc_extent(desc.width);
+        let height = percent_to_ndc_extent(desc.height);
+        self.verticies = [
+            Vertex {
+                // Top Left
+                position: [x, y, 0.0],
+                color: desc.color,
+            },
+            Vertex {
+                // Top Right
+                position: [x + width, y, 0.0],
+                color: desc.color,
+            },
+            Vertex {
+                // Bottom Left
+                position: [x, y - height, 0.0],
+                color: desc.color,
+            },
+            Vertex {
+                // Bottom Right
+                position: [x + width, y - height, 0.0],
+                color: desc.color,
+            },
+        ];
+        self.indicies = &[0, 2, 1, 2, 3, 1];
     }
 }
 
@@ -77,38 +101,52 @@ impl<'a> Widget for SquareWidget<'a> {
     }
     fn set_color(&mut self, color: [f32; 4]) {
         self.description.color = color;
+        self.tessellate();
     }
-    fn get_color(&mut self) -> [f32; 4] {
+    fn get_color(&self) -> [f32; 4] {
         self.description.color
     }
     fn set_x(&mut self, x: f32) {
-        self.description.x = x
+        self.description.x = x;
+        self.tessellate();
     }
     fn get_x(&self) -> f32 {
         self.description.x
     }
     fn set_y(&mut self, y: f32) {
-        self.description.y = y
+        self.description.y = y;
+        self.tessellate();
     }
     fn get_y(&self) -> f32 {
         self.description.y
     }
 
     fn set_width(&mut self, width: f32) {
-        self.description.width = width
+        self.description.width = width;
+        self.tessellate();
     }
 
-    fn get_width(&mut self) -> f32 {
+    fn get_width(&self) -> f32 {
         self.description.width
     }
 
     fn set_height(&mut self, height: f32) {
-        self.description.height = height
+        self.description.height = height;
+        self.tessellate();
     }
 
-    fn get_height(&mut self) -> f32 {
+    fn get_height(&self) -> f32 {
         self.description.height
     }
+    fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.description.blend_mode = blend_mode
+    }
+    fn get_blend_mode(&self) -> BlendMode {
+        self.description.blend_mode
+    }
+    fn get_shadow(&self) -> Option<ShadowDesc> {
+        self.description.shadow
+    }
     fn get_vertices(&self) -> &[Vertex] {
         self.verticies.as_slice()
     }
@@ -125,22 +163,10 @@ impl<'a> Widget for SquareWidget<'a> {
 
 impl<'a> WidgetRender for SquareWidget<'a> {
     fn get_pipeline(&self, app: &AppRender) -> RenderPipeline {
-        let device = app.get_device();
-        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: None,
-            bind_group_layouts: &[],
-            push_constant_ranges: &[],
-        });
-        let shader = self.get_shader(app);
-        device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[self.get_vertex_desc()],
-            },
-            primitive: wgpu::PrimitiveState {
+        create_color_pipeline(
+            app,
+            self,
+            wgpu::PrimitiveState {
                 topology: wgpu::PrimitiveTopology::TriangleStrip,
                 strip_index_format: Some(wgpu::IndexFormat::Uint16),
                 front_face: wgpu::FrontFace::Ccw,
@@ -149,32 +175,10 @@ impl<'a> WidgetRender for SquareWidget<'a> {
                 polygon_mode: wgpu::PolygonMode::Fill,
                 conservative: false,
             },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: app.config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
-        })
-    }
-    fn get_shader(&self, app: &AppRender) -> wgpu::ShaderModule {
-        app.get_device()
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: None,
-                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(
-                    "../shader/main.wgsl"
-                ))),
-            })
+        )
+    }
+    fn get_shader(&self, app: &AppRender) -> Rc<wgpu::ShaderModule> {
+        app.get_shader_module(include_str!("../shader/main.wgsl"))
     }
 }
 
@@ -183,3 +187,39 @@ impl<'a> WidgetVertex for SquareWidget<'a> {
         Vertex::desc()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn setters_rebuild_the_vertices() {
+        let mut square = SquareWidget::create();
+        square.set_x(50.0);
+        square.set_y(50.0);
+        square.set_width(50.0);
+        square.set_height(25.0);
+        square.set_color([0.0, 0.0, 1.0, 1.0]);
+        let expected = SquareWidget::new(SquareWidgetDesc {
+            width: 50.0,
+            height: 25.0,
+            x: 50.0,
+            y: 50.0,
+            color: [0.0, 0.0, 1.0, 1.0],
+            ..Default::default()
+        });
+        let positions = |i: &SquareWidget| {
+            i.get_vertices()
+                .iter()
+                .map(|i| i.position)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(positions(&square), positions(&expected));
+        assert_eq!(positions(&square)[3], [1.0, -0.5, 0.0]);
+        assert!(square
+            .get_vertices()
+            .iter()
+            .all(|i| i.color == [0.0, 0.0, 1.0, 1.0]));
+        assert_eq!(square.get_indices(), expected.get_indices());
+    }
+}
@@ -0,0 +1,101 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use notes_editor::{app::AppRender, config::AppConfig, widgets::WidgetRender};
+use winit::dpi::PhysicalSize;
+
+pub const CAPTURE_SIZE: PhysicalSize<u32> = PhysicalSize::new(64, 32);
+
+/*
+ * Golden images live in tests/golden and are compared per channel.
+ * Run with UPDATE_GOLDEN=1 to (re)write them from the current output.
+ * On mismatch the actual output and a diff (mismatches in red) are
+ * written to the test target directory.
+ */
+
+pub fn headless_renderer() -> AppRender {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime
+        .block_on(AppRender::new_headless(CAPTURE_SIZE, &AppConfig::default()))
+        .expect("Headless rendering needs a GPU adapter")
+}
+
+pub fn assert_renders_like(widgets: Vec<Box<dyn WidgetRender>>, golden_path: &str, tolerance: u8) {
+    let mut renderer = headless_renderer();
+    for i in widgets {
+        renderer.add_widget(i);
+    }
+    assert_capture_like(&renderer, golden_path, tolerance);
+}
+
+pub fn assert_capture_like(renderer: &AppRender, golden_path: &str, tolerance: u8) {
+    let actual = renderer.capture().unwrap();
+    let golden_path = Path::new(env!("CARGO_MANIFEST_DIR")).join(golden_path);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        write_png(&golden_path, &actual);
+        return;
+    }
+    let expected = read_png(&golden_path);
+    let mut mismatches = 0;
+    let diff: Vec<u8> = actual
+        .chunks(4)
+        .zip(expected.chunks(4))
+        .flat_map(|(a, e)| {
+            if a.iter().zip(e).any(|(a, e)| a.abs_diff(*e) > tolerance) {
+                mismatches += 1;
+                [255, 0, 0, 255]
+            } else {
+                let gray = (a[0] as u16 + a[1] as u16 + a[2] as u16) / 6;
+                [gray as u8, gray as u8, gray as u8, 255]
+            }
+        })
+        .collect();
+    if mismatches > 0 {
+        let name = golden_path.file_stem().unwrap().to_string_lossy();
+        let out = Path::new(env!("CARGO_TARGET_TMPDIR"));
+        let actual_path = out.join(format!("{name}.actual.png"));
+        let diff_path = out.join(format!("{name}.diff.png"));
+        write_png(&actual_path, &actual);
+        write_png(&diff_path, &diff);
+        panic!(
+            "{mismatches} pixels differ from {} by more than {tolerance}, see {} and {}",
+            golden_path.display(),
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+fn read_png(path: &Path) -> Vec<u8> {
+    let file = File::open(path).unwrap_or_else(|_| {
+        panic!(
+            "Missing golden image {}, run with UPDATE_GOLDEN=1 to create it",
+            path.display()
+        )
+    });
+    let mut reader = png::Decoder::new(file).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(
+        (info.width, info.height, info.color_type),
+        (
+            CAPTURE_SIZE.width,
+            CAPTURE_SIZE.height,
+            png::ColorType::Rgba
+        ),
+        "Golden image {} has the wrong size or format",
+        path.display()
+    );
+    pixels
+}
+
+fn write_png(path: &Path, pixels: &[u8]) {
+    let file = BufWriter::new(File::create(path).unwrap());
+    let mut encoder = png::Encoder::new(file, CAPTURE_SIZE.width, CAPTURE_SIZE.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .unwrap()
+        .write_image_data(pixels)
+        .unwrap();
+}
@@ -0,0 +1,530 @@
+mod common;
+
+use std::{fs, path::Path, rc::Rc, time::Duration};
+
+use common::{assert_capture_like, assert_renders_like, headless_renderer, CAPTURE_SIZE};
+use notes_editor::{
+    app::AppRender,
+    config::AppConfig,
+    pipeline::BlendMode,
+    record::{record, Recording},
+    theme::Theme,
+    widgets::{
+        color_picker::{ColorPickerWidget, ColorPickerWidgetDesc},
+        path::{LineStyle, PathWidget, PathWidgetDesc},
+        progress_bar::{ProgressBarWidget, ProgressBarWidgetDesc},
+        ring::{RingWidget, RingWidgetDesc},
+        shadow::ShadowDesc,
+        spinner::{SpinnerWidget, SpinnerWidgetDesc},
+        square::{SquareWidget, SquareWidgetDesc},
+        Widget, WidgetRender,
+    },
+};
+
+// Room for rounding differences between GPUs and drivers
+const TOLERANCE: u8 = 2;
+
+#[test]
+fn empty_canvas() {
+    assert_renders_like(vec![], "tests/golden/empty.png", TOLERANCE);
+}
+
+#[test]
+fn square_top_left() {
+    let square = SquareWidget::new(SquareWidgetDesc {
+        width: 50.0,
+        height: 50.0,
+        x: 0.0,
+        y: 0.0,
+        color: [1.0, 1.0, 1.0, 1.0],
+        ..Default::default()
+    })
+    .build();
+    assert_renders_like(vec![square], "tests/golden/square_top_left.png", TOLERANCE);
+}
+
+#[test]
+fn overlapping_colors() {
+    let blue = SquareWidget::new(SquareWidgetDesc {
+        width: 50.0,
+        height: 50.0,
+        x: 25.0,
+        y: 25.0,
+        color: [0.0, 0.0, 1.0, 1.0],
+        ..Default::default()
+    })
+    .build();
+    let red = SquareWidget::new(SquareWidgetDesc {
+        width: 50.0,
+        height: 25.0,
+        x: 50.0,
+        y: 50.0,
+        color: [1.0, 0.0, 0.0, 1.0],
+        ..Default::default()
+    })
+    .build();
+    assert_renders_like(
+        vec![blue, red],
+        "tests/golden/overlapping_colors.png",
+        TOLERANCE,
+    );
+}
+
+#[test]
+fn alpha_blend_over_square() {
+    let blue = SquareWidget::new(SquareWidgetDesc {
+        width: 50.0,
+        height: 50.0,
+        x: 0.0,
+        y: 0.0,
+        color: [0.0, 0.0, 1.0, 1.0],
+        ..Default::default()
+    })
+    .build();
+    let red = SquareWidget::new(SquareWidgetDesc {
+        width: 50.0,
+        height: 50.0,
+        x: 25.0,
+        y: 25.0,
+        color: [1.0, 0.0, 0.0, 0.5],
+        blend_mode: BlendMode::AlphaBlend,
+        ..Default::default()
+    })
+    .build();
+    assert_renders_like(
+        vec![blue, red],
+        "tests/golden/alpha_blend_over_square.png",
+        TOLERANCE,
+    );
+}
+
+#[test]
+fn color_picker() {
+    let picker = ColorPickerWidget::new(ColorPickerWidgetDesc {
+        width: 100.0,
+        height: 100.0,
+        x: 0.0,
+        y: 0.0,
+        color: [0.2, 0.6, 1.0, 1.0],
+        ..Default::default()
+    })
+    .build();
+    assert_renders_like(vec![picker], "tests/golden/color_picker.png", TOLERANCE);
+}
+
+#[test]
+fn dashed_and_dotted_paths() {
+    let path = |y, style| {
+        PathWidget::new(PathWidgetDesc {
+            points: vec![[10.0, y], [90.0, y]],
+            width: 10.0,
+            color: [0.0, 0.0, 0.0, 1.0],
+            style,
+            ..Default::default()
+        })
+        .build()
+    };
+    assert_renders_like(
+        vec![
+            path(
+                25.0,
+                LineStyle::Dashed {
+                    on: 20.0,
+                    off: 10.0,
+                },
+            ),
+            path(75.0, LineStyle::Dotted),
+        ],
+        "tests/golden/dashed_and_dotted_paths.png",
+        TOLERANCE,
+    );
+}
+
+#[test]
+fn square_with_shadow() {
+    let square = SquareWidget::new(SquareWidgetDesc {
+        width: 40.0,
+        height: 40.0,
+        x: 20.0,
+        y: 20.0,
+        color: [1.0, 1.0, 1.0, 1.0],
+        shadow: Some(ShadowDesc {
+            offset: [10.0, 10.0],
+            blur: 10.0,
+            color: [0.0, 0.0, 0.0, 0.5],
+        }),
+        ..Default::default()
+    })
+    .build();
+    assert_renders_like(
+        vec![square],
+        "tests/golden/square_with_shadow.png",
+        TOLERANCE,
+    );
+}
+
+#[test]
+fn recycled_buffers_render_like_fresh_ones() {
+    let mut renderer = headless_renderer();
+    let before = renderer.buffer_stats();
+    // The square drawn below takes over buffers still holding the red one
+    for _ in 0..3 {
+        let index = renderer.add_widget(ColorPickerWidget::create().build());
+        renderer.remove_widget(index);
+        let index = renderer.add_widget(
+            SquareWidget::new(SquareWidgetDesc {
+                width: 100.0,
+                height: 100.0,
+                color: [1.0, 0.0, 0.0, 1.0],
+                ..Default::default()
+            })
+            .build(),
+        );
+        renderer.remove_widget(index);
+    }
+    let stats = renderer.buffer_stats();
+    assert_eq!(stats.allocated - before.allocated, 4);
+    assert_eq!(stats.reused - before.reused, 8);
+    renderer.add_widget(
+        SquareWidget::new(SquareWidgetDesc {
+            width: 50.0,
+            height: 50.0,
+            x: 0.0,
+            y: 0.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            ..Default::default()
+        })
+        .build(),
+    );
+    assert_capture_like(&renderer, "tests/golden/square_top_left.png", TOLERANCE);
+}
+
+#[test]
+fn widgets_added_before_the_gpu_are_uploaded() {
+    let mut renderer = AppRender::new_offline(CAPTURE_SIZE);
+    renderer.add_widget(
+        SquareWidget::new(SquareWidgetDesc {
+            width: 50.0,
+            height: 50.0,
+            x: 0.0,
+            y: 0.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            ..Default::default()
+        })
+        .build(),
+    );
+    assert!(renderer.capture().is_err());
+    tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(renderer.attach_headless(&AppConfig::default()))
+        .unwrap();
+    assert_capture_like(&renderer, "tests/golden/square_top_left.png", TOLERANCE);
+}
+
+#[test]
+fn incremental_redraw_matches_full_redraw() {
+    let square = |x, y, color| {
+        SquareWidget::new(SquareWidgetDesc {
+            width: 30.0,
+            height: 30.0,
+            x,
+            y,
+            color,
+            shadow: Some(ShadowDesc {
+                offset: [5.0, 5.0],
+                blur: 5.0,
+                color: [0.0, 0.0, 0.0, 0.5],
+            }),
+            ..Default::default()
+        })
+        .build()
+    };
+    let stroke = PathWidget::new(PathWidgetDesc {
+        points: vec![[10.0, 90.0], [50.0, 60.0], [90.0, 90.0]],
+        width: 4.0,
+        color: [0.0, 0.0, 1.0, 1.0],
+        ..Default::default()
+    })
+    .build();
+    let expected = {
+        // Two renderers at once upset the GL backend on some drivers
+        let mut full = headless_renderer();
+        full.add_widget(square(20.0, 40.0, [1.0, 1.0, 1.0, 1.0]));
+        full.add_widget(stroke.clone());
+        full.capture().unwrap()
+    };
+    let mut incremental = headless_renderer();
+    incremental.add_widget(square(10.0, 10.0, [1.0, 1.0, 1.0, 1.0]));
+    incremental.add_widget(square(50.0, 20.0, [1.0, 0.0, 0.0, 1.0]));
+    incremental.capture().unwrap();
+    // Moving and removing have to clear where the widgets used to be
+    incremental.update_widget(0, square(20.0, 40.0, [1.0, 1.0, 1.0, 1.0]));
+    incremental.capture().unwrap();
+    incremental.remove_widget(1);
+    incremental.add_widget(stroke);
+    assert!(incremental.get_damage().unwrap().width < 100.0);
+    let actual = incremental.capture().unwrap();
+    assert_eq!(incremental.get_damage(), None);
+    assert!(
+        actual == expected,
+        "incremental redraw differs from a full one"
+    );
+}
+
+#[test]
+fn dark_background() {
+    let mut renderer = headless_renderer();
+    renderer.add_widget(
+        SquareWidget::new(SquareWidgetDesc {
+            width: 50.0,
+            height: 50.0,
+            x: 25.0,
+            y: 25.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            ..Default::default()
+        })
+        .build(),
+    );
+    renderer.capture().unwrap();
+    renderer.set_background(Theme::DARK.background);
+    assert_capture_like(&renderer, "tests/golden/dark_background.png", TOLERANCE);
+}
+
+#[test]
+fn progress_bars() {
+    let bar = |y, value| {
+        ProgressBarWidget::new(ProgressBarWidgetDesc {
+            width: 100.0,
+            height: 25.0,
+            x: 0.0,
+            y,
+            color: [0.0, 0.0, 1.0, 1.0],
+            track_color: [1.0, 1.0, 1.0, 1.0],
+            value,
+            ..Default::default()
+        })
+        .build()
+    };
+    let mut renderer = headless_renderer();
+    renderer.add_widget(bar(12.5, Some(0.25)));
+    renderer.add_widget(bar(62.5, None));
+    renderer.capture().unwrap();
+    // Only the indeterminate bar moves, it now covers 0.5 - 0.8
+    renderer.update(Duration::from_secs(1)).unwrap();
+    assert!(renderer.needs_redraw());
+    assert_capture_like(&renderer, "tests/golden/progress_bars.png", TOLERANCE);
+}
+
+#[test]
+fn rings_and_spinner() {
+    // Half the capture's width is as many pixels as its full height
+    let ring = |x, inner_radius| {
+        RingWidget::new(RingWidgetDesc {
+            width: 25.0,
+            height: 50.0,
+            x,
+            y: 0.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            inner_radius,
+            ..Default::default()
+        })
+        .build()
+    };
+    let spinner = SpinnerWidget::new(SpinnerWidgetDesc {
+        width: 25.0,
+        height: 50.0,
+        x: 37.5,
+        y: 50.0,
+        color: [0.0, 0.0, 1.0, 1.0],
+        thickness: 0.5,
+        spinning: true,
+        ..Default::default()
+    })
+    .build();
+    let mut renderer = headless_renderer();
+    renderer.add_widget(ring(0.0, 0.0));
+    renderer.add_widget(ring(75.0, 0.5));
+    renderer.add_widget(spinner);
+    renderer.capture().unwrap();
+    renderer.update(Duration::from_millis(500)).unwrap();
+    assert!(renderer.needs_redraw());
+    assert_capture_like(&renderer, "tests/golden/rings_and_spinner.png", TOLERANCE);
+}
+
+#[test]
+fn reordering_redraws_the_overlap() {
+    let square = |x, y, color| {
+        SquareWidget::new(SquareWidgetDesc {
+            width: 50.0,
+            height: 50.0,
+            x,
+            y,
+            color,
+            ..Default::default()
+        })
+        .build()
+    };
+    let blue = square(10.0, 10.0, [0.0, 0.0, 1.0, 1.0]);
+    let red = square(40.0, 40.0, [1.0, 0.0, 0.0, 1.0]);
+    let expected = {
+        let mut full = headless_renderer();
+        full.add_widget(red.clone());
+        full.add_widget(blue.clone());
+        full.capture().unwrap()
+    };
+    let mut reordered = headless_renderer();
+    reordered.add_widget(blue);
+    reordered.add_widget(red);
+    reordered.capture().unwrap();
+    assert_eq!(reordered.send_to_back(1), 0);
+    assert!(reordered.capture().unwrap() == expected);
+}
+
+#[test]
+fn widget_types_share_shader_modules() {
+    let renderer = headless_renderer();
+    let square = SquareWidget::new(SquareWidgetDesc::default());
+    let path = PathWidget::new(PathWidgetDesc::default());
+    assert!(Rc::ptr_eq(
+        &square.get_shader(&renderer),
+        &path.get_shader(&renderer)
+    ));
+    let other = renderer.get_shader_module(
+        "@vertex fn vs_main() -> @builtin(position) vec4<f32> { return vec4<f32>(); }",
+    );
+    assert!(!Rc::ptr_eq(&other, &square.get_shader(&renderer)));
+}
+
+#[test]
+fn render_stats_count_drawn_and_culled_widgets() {
+    let square = |x, shadow| {
+        SquareWidget::new(SquareWidgetDesc {
+            width: 10.0,
+            height: 10.0,
+            x,
+            y: 10.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            shadow,
+            ..Default::default()
+        })
+        .build()
+    };
+    let mut renderer = headless_renderer();
+    renderer.add_widget(square(10.0, None));
+    renderer.add_widget(square(
+        70.0,
+        Some(ShadowDesc {
+            offset: [1.0, 1.0],
+            blur: 1.0,
+            color: [0.0, 0.0, 0.0, 0.5],
+        }),
+    ));
+    renderer.capture().unwrap();
+    let stats = renderer.last_stats();
+    assert_eq!((stats.widgets_drawn, stats.widgets_culled), (2, 0));
+    // A full redraw clears instead of drawing the background
+    assert_eq!(stats.draw_calls, 3);
+    renderer.edit_widget(0, |i| i.set_color([1.0, 0.0, 0.0, 1.0]));
+    renderer.capture().unwrap();
+    let stats = renderer.last_stats();
+    assert_eq!((stats.widgets_drawn, stats.widgets_culled), (1, 1));
+    assert_eq!(stats.draw_calls, 2);
+    renderer.capture().unwrap();
+    let stats = renderer.last_stats();
+    assert_eq!(
+        (stats.widgets_drawn, stats.widgets_culled, stats.draw_calls),
+        (0, 2, 0)
+    );
+}
+
+#[test]
+fn off_screen_widgets_are_culled() {
+    let square = |x, y| {
+        SquareWidget::new(SquareWidgetDesc {
+            width: 20.0,
+            height: 20.0,
+            x,
+            y,
+            color: [1.0, 1.0, 1.0, 1.0],
+            ..Default::default()
+        })
+        .build()
+    };
+    let mut renderer = headless_renderer();
+    renderer.add_widget(square(-500.0, 20.0));
+    renderer.add_widget(square(1000.0, 1000.0));
+    // Only partly on-screen
+    renderer.add_widget(square(90.0, -10.0));
+    renderer.capture().unwrap();
+    let stats = renderer.last_stats();
+    assert_eq!((stats.widgets_drawn, stats.widgets_culled), (1, 2));
+    // Moving about off-screen leaves nothing to redraw
+    renderer.update_widget(1, square(2000.0, 0.0));
+    assert_eq!(renderer.get_damage(), None);
+    assert!(!renderer.needs_redraw());
+}
+
+#[test]
+fn recordings_are_reproducible() {
+    let record_sweep = |dir: &Path| {
+        let mut renderer = headless_renderer();
+        renderer.add_widget(
+            ProgressBarWidget::new(ProgressBarWidgetDesc {
+                width: 100.0,
+                height: 50.0,
+                color: [0.0, 0.0, 1.0, 1.0],
+                track_color: [1.0, 1.0, 1.0, 1.0],
+                value: None,
+                ..Default::default()
+            })
+            .build(),
+        );
+        let recording = Recording {
+            duration: Duration::from_secs(1),
+            fps: 4,
+        };
+        let paths = record(&mut renderer, recording, dir).unwrap();
+        paths
+            .iter()
+            .map(|i| fs::read(i).unwrap())
+            .collect::<Vec<_>>()
+    };
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("recording");
+    let first = record_sweep(&out.join("first"));
+    let second = record_sweep(&out.join("second"));
+    assert_eq!(first.len(), 4);
+    assert!(out.join("first/frame_00003.png").exists());
+    // The sweep moves between frames, identically in both recordings
+    assert!(first.windows(2).all(|i| i[0] != i[1]));
+    assert_eq!(first, second);
+}
+
+#[test]
+fn content_margin() {
+    let square = |color| {
+        SquareWidget::new(SquareWidgetDesc {
+            width: 100.0,
+            height: 100.0,
+            color,
+            ..Default::default()
+        })
+        .build()
+    };
+    let mut renderer = headless_renderer();
+    renderer.set_content_margin((4.0, 8.0, 4.0, 16.0));
+    renderer.add_widget(square([1.0, 1.0, 1.0, 1.0]));
+    renderer.add_widget(square([0.0, 0.0, 1.0, 1.0]));
+    renderer.capture().unwrap();
+    // Partial redraws stay inside the content too
+    renderer.update_widget(
+        1,
+        SquareWidget::new(SquareWidgetDesc {
+            width: 50.0,
+            height: 50.0,
+            color: [0.0, 0.0, 1.0, 1.0],
+            ..Default::default()
+        })
+        .build(),
+    );
+    assert_capture_like(&renderer, "tests/golden/content_margin.png", TOLERANCE);
+}